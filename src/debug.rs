@@ -0,0 +1,208 @@
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    Ball, GameConfig, GameMode, GameState, MatchStats, Paddle, Position, PreviousPosition,
+    RallySpeed, Score, Shape, Velocity,
+};
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin)
+        .init_resource::<DebugOverlay>()
+        .init_resource::<ShowCollisionBoxes>()
+        .add_systems(Startup, spawn_debug_text)
+        .add_systems(
+            Update,
+            (
+                toggle_debug_overlay,
+                start_demo_match.run_if(in_state(GameState::MainMenu)),
+                toggle_collision_boxes,
+                draw_collision_boxes
+                    .after(toggle_collision_boxes)
+                    .run_if(|boxes: Res<ShowCollisionBoxes>| boxes.0),
+                show_debug_overlay.after(toggle_debug_overlay),
+                update_debug_text
+                    .after(toggle_debug_overlay)
+                    .run_if(|overlay: Res<DebugOverlay>| overlay.0),
+                draw_velocity_arrows
+                    .after(toggle_debug_overlay)
+                    .run_if(|overlay: Res<DebugOverlay>| overlay.0),
+            ),
+        );
+    }
+}
+
+// Whether the F3 developer overlay is showing
+#[derive(Resource, Default)]
+pub struct DebugOverlay(pub bool);
+
+// Whether the F4 outlines around everything that can be collided with are
+// showing
+#[derive(Resource, Default)]
+pub struct ShowCollisionBoxes(pub bool);
+
+#[derive(Component)]
+struct DebugText;
+
+fn spawn_debug_text(mut commands: Commands) {
+    commands.spawn((
+        DebugText,
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 18.0,
+                    color: Color::YELLOW,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+fn toggle_debug_overlay(keyboard_input: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        overlay.0 = !overlay.0;
+    }
+}
+
+// F5 on the main menu sets the AI playing against itself, which is handy for
+// seeing how the difficulties compare
+fn start_demo_match(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        *mode = GameMode::DemoMatch;
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn toggle_collision_boxes(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut boxes: ResMut<ShowCollisionBoxes>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        boxes.0 = !boxes.0;
+    }
+}
+
+// Outlines the box collisions are actually checked against, which isn't
+// always what's drawn (the ball is round, for one). Anything that moves also
+// gets a line back to where it was last step, the path it's swept along.
+fn draw_collision_boxes(
+    mut gizmos: Gizmos,
+    things: Query<(&Position, &Shape, Option<&PreviousPosition>)>,
+) {
+    for (position, shape, previous_position) in &things {
+        gizmos.rect_2d(position.0, 0., shape.0, Color::YELLOW);
+        if let Some(previous_position) = previous_position {
+            gizmos.line_2d(previous_position.0, position.0, Color::ORANGE_RED);
+        }
+    }
+}
+
+// How long an arrow is for something moving at a given speed: it points to
+// where that thing will be in this many seconds if nothing changes. And how
+// long the two strokes of its head are, in pixels.
+const VELOCITY_ARROW_SECS: f32 = 0.25;
+const ARROW_HEAD_LENGTH: f32 = 6.;
+
+// Arrows out from the balls and paddles showing which way and how fast
+// they're going, to check bounces and paddle spin by eye
+fn draw_velocity_arrows(
+    mut gizmos: Gizmos,
+    balls: Query<(&Position, &Velocity, &RallySpeed), With<Ball>>,
+    paddles: Query<(&Position, &Velocity), With<Paddle>>,
+    config: Res<GameConfig>,
+) {
+    for (position, velocity, rally_speed) in &balls {
+        // Velocity only holds the heading, the rally speed says how fast
+        let actual = velocity.0.normalize_or_zero() * rally_speed.0;
+        draw_arrow(&mut gizmos, position.0, actual * VELOCITY_ARROW_SECS, Color::CYAN);
+    }
+
+    // A paddle's velocity is in paddle speeds rather than pixels
+    for (position, velocity) in &paddles {
+        let actual = velocity.0 * config.paddle_speed;
+        draw_arrow(&mut gizmos, position.0, actual * VELOCITY_ARROW_SECS, Color::LIME_GREEN);
+    }
+}
+
+fn draw_arrow(gizmos: &mut Gizmos, start: Vec2, vector: Vec2, color: Color) {
+    // Nothing to point at when standing still
+    let Some(direction) = vector.try_normalize() else {
+        return;
+    };
+
+    let end = start + vector;
+    gizmos.line_2d(start, end, color);
+    for angle in [2.6, -2.6] {
+        let head = Vec2::from_angle(angle).rotate(direction) * ARROW_HEAD_LENGTH;
+        gizmos.line_2d(end, end + head, color);
+    }
+}
+
+// Hide the text outright when the overlay is off so it never shows numbers
+// from the last time it was updated
+fn show_debug_overlay(
+    overlay: Res<DebugOverlay>,
+    mut text: Query<&mut Visibility, With<DebugText>>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut text {
+        *visibility = if overlay.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_debug_text(
+    diagnostics: Res<DiagnosticsStore>,
+    balls: Query<(&Velocity, &RallySpeed), With<Ball>>,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    mut text: Query<&mut Text, With<DebugText>>,
+) {
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+
+    let mut lines = vec![format!("FPS: {fps:.0}")];
+    for (velocity, rally_speed) in &balls {
+        // Velocity only holds the heading, the rally speed says how fast
+        let actual = velocity.0.normalize_or_zero() * rally_speed.0;
+        lines.push(format!(
+            "Ball: ({:.2}, {:.2}) speed {:.2}",
+            actual.x,
+            actual.y,
+            actual.length()
+        ));
+    }
+    lines.push(format!("Score: {} - {}", score.left, score.right));
+    lines.push(format!("Rally: {}", stats.current_rally));
+
+    for mut text in &mut text {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -1,437 +1,4071 @@
+// Bevy system parameters routinely trip this lint
+#![allow(clippy::type_complexity)]
+
 use bevy::{
+    asset::LoadState,
+    ecs::system::SystemParam,
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
     prelude::*,
     sprite::collide_aabb::{collide, Collision}, // TODO: Replace with Rapier 2D Physics
-    sprite::MaterialMesh2dBundle,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::{PresentMode, WindowFocused},
 };
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::VecDeque, ops::RangeInclusive, time::Duration};
 // use bevy_rapier2d::prelude::*;
 // With the current sprite collide_aabb there's an issue where the velocity of the ball exceeds the speed of the collision detection.
 // This causes the ball to pass through the paddle.
 // Rapier 2D Physics has a much better collision detection system. We'll use that instead. 
+mod debug;
 mod menu;
+mod save;
+#[cfg(test)]
+mod testing;
 
+// How wide the ball is unless `GameConfig::ball_size` says otherwise
 const BALL_WIDTH: f32 = 10.;
-const BALL_SPEED: f32 = 5.; 
-const PADDLE_SPEED: f32 = 5.;
-const PADDLE_WIDTH: f32 = 10.;
-const PADDLE_HEIGHT: f32 = 50.;
-const GUTTER_HEIGHT: f32 = 20.;
+// Gap between each paddle and its edge of the screen
+const PADDLE_PADDING: f32 = 50.;
+// How tall a handicapped paddle is compared to a normal one
+const HANDICAP_SCALE: f32 = 0.6;
+// Thickness of the practice wall
+const WALL_WIDTH: f32 = 20.;
+// How long the countdown before each serve lasts
+const SERVE_COUNTDOWN_SECS: f32 = 3.;
+// Points needed to win a match, unless `GameConfig::win_target` says otherwise
+const WINNING_SCORE: u32 = 11;
+// Every this many points in a row is worth a bonus point
+const STREAK_FOR_BONUS: u32 = 3;
+// Misses allowed in survival mode
+const STARTING_LIVES: u32 = 3;
+// How long a timed match lasts
+const MATCH_SECS: f32 = 60.;
+// Steepest angle (in radians from horizontal) the ball can leave a paddle at
+const MAX_BOUNCE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+// When serving from the paddle, the gap left between the held ball and the
+// paddle's face, and how long the AI holds on before letting go
+const HELD_BALL_GAP: f32 = 2.;
+const AI_SERVE_SECS: f32 = 1.;
+// How far from where it's going the AI paddle starts to ease off, in pixels
+const AI_EASE_DISTANCE: f32 = 40.;
+// How fast a ball hit by a paddle at full speed curves, in radians per second,
+// and how quickly that wears off (the fraction left after a second is e to
+// minus this)
+const SPIN_PER_PADDLE_SPEED: f32 = 0.6;
+const SPIN_DECAY: f32 = 1.;
+// How hard assist mode bends the ball toward the player's paddle, in change
+// of slope per second for each pixel the ball is off
+const ASSIST_STRENGTH: f32 = 0.004;
+
+// The dashed line down the middle of the court. Each dash plus the gap after
+// it takes up roughly this much height, so taller windows get more dashes.
+const CENTER_LINE_WIDTH: f32 = 4.;
+const CENTER_LINE_SPACING: f32 = 30.;
+
+// How near the front each kind of thing is drawn, from the center line at the
+// back to the serve arrow at the front. The UI goes over the top of all of it.
+const CENTER_LINE_Z: f32 = -1.;
+const GUTTER_Z: f32 = 0.;
+const BALL_PATH_Z: f32 = 0.5;
+const POWER_UP_Z: f32 = 1.;
+const PADDLE_Z: f32 = 2.;
+const PARTICLE_Z: f32 = 2.4;
+const TRAIL_Z: f32 = 2.5;
+const BALL_Z: f32 = 3.;
+const SERVE_ARROW_Z: f32 = 3.5;
+// In front of everything on the court, so nothing shows past its edges
+const LETTERBOX_Z: f32 = 10.;
 
 // Colors 
-const BALL_COLOR: Color = Color::rgb(1., 0., 0.);
+const CENTER_LINE_COLOR: Color = Color::rgba(1., 1., 1., 0.5);
+
+// One half of the court. Paddles (and the wall in practice) carry the side
+// they're on.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    // Which way along the x axis this side is from the middle of the court
+    fn direction(self) -> f32 {
+        match self {
+            Side::Left => -1.,
+            Side::Right => 1.,
+        }
+    }
 
-enum Scorer {
-    Ai,
-    Player,
+    fn opposite(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    // Where a paddle on this side sits, a little way in from the end
+    fn paddle_x(self, court_width: f32) -> f32 {
+        (court_width / 2. - PADDLE_PADDING) * self.direction()
+    }
+
+    // Which way along the y axis the gutter this side guards in four paddle
+    // mode is: the left side takes the top and the right side the bottom
+    fn gutter_direction(self) -> f32 {
+        -self.direction()
+    }
+
+    // Where this side's gutter paddle sits, a little way out from its gutter
+    fn gutter_paddle_y(self, court_height: f32, gutter_height: f32) -> f32 {
+        (court_height / 2. - gutter_height - PADDLE_PADDING / 2.) * self.gutter_direction()
+    }
 }
 
 #[derive(Component)]
-struct PlayerScore;
+struct LeftScoreText;
 
 #[derive(Component)]
-struct AiScore;
+struct RightScoreText;
+
 #[derive(Component)]
-struct PlayerScoreboard;
+struct Ball;
 
+// A dark patch drawn just behind and below a ball to give it some depth. It
+// follows the ball it belongs to around and goes when the ball does.
 #[derive(Component)]
-struct AiScoreboard;
+struct BallShadow(Entity);
 
+// A ball that has left the court and is waiting to be served again
 #[derive(Component)]
-struct Ball;
+struct OutOfPlay;
 
+// A ball waiting on the face of `paddle` to be served, when serving from the
+// paddle. It goes wherever the paddle goes until it's launched.
 #[derive(Component)]
-struct Paddle;
+struct HeldBall {
+    paddle: Entity,
+    secs_held: f32,
+}
 
+// A ball sitting still until `serve_balls` sends it off along this heading:
+// at the center until the serve countdown finishes, or just let go of by the
+// paddle that was holding it. Balls only ever start moving from here.
 #[derive(Component)]
-struct Gutter;
+struct AwaitingServe(Vec2);
+
+// Which way a ball is going, or for one still waiting to be served, which way
+// it's about to go
+fn ball_heading(velocity: &Velocity, serve: Option<&AwaitingServe>) -> Vec2 {
+    serve.map_or(velocity.0, |serve| serve.0)
+}
 
 #[derive(Component)]
-struct Player;
+struct Paddle;
 
+// One of the extra paddles across the top and bottom of the court in four
+// paddle mode. It slides along in front of its gutter rather than up and down.
 #[derive(Component)]
-struct Ai;
+struct GutterPaddle;
 
-#[derive(Event)]
-struct Scored(Scorer);
+#[derive(Component)]
+struct Gutter;
 
-#[derive(Resource, Default)]
-struct Score {
-    ai: u32,
-    player: u32,
+// How deep the invisible strips just outside the court that score a point
+// are. Deep enough that no ball gets across one in a single step.
+const SCORE_WALL_DEPTH: f32 = 100.;
+
+// An invisible strip just past one side's end of the court (or, in four
+// paddle mode, past the gutter it guards). A ball touching one is a point
+// against that side. It has no `CollisionLayer`, so nothing bounces off it.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum ScoreWall {
+    End,
+    Gutter,
 }
 
+impl ScoreWall {
+    // Where a score wall guarding `side` sits and how big it is. The ones at
+    // the ends overhang the corners so a ball can't slip out between them.
+    fn placement(self, side: Side, court: &CourtDimensions) -> (Vec2, Vec2) {
+        match self {
+            ScoreWall::End => (
+                Vec2::new((court.width / 2. + SCORE_WALL_DEPTH / 2.) * side.direction(), 0.),
+                Vec2::new(SCORE_WALL_DEPTH, court.height + SCORE_WALL_DEPTH * 2.),
+            ),
+            ScoreWall::Gutter => (
+                Vec2::new(
+                    0.,
+                    (court.height / 2. + SCORE_WALL_DEPTH / 2.) * side.gutter_direction(),
+                ),
+                Vec2::new(court.width, SCORE_WALL_DEPTH),
+            ),
+        }
+    }
+}
+
+// One dot of the line showing where the ball is headed in practice mode
 #[derive(Component)]
-struct Position(Vec2);
+struct BallPathDot;
 
+// The solid left side of the court in practice mode, which the ball always
+// bounces straight back off
 #[derive(Component)]
-struct Shape(Vec2);
+struct Wall;
 
+// One dash of the line down the middle. Purely decoration, so it has no
+// `Position` or `Shape` and the ball passes straight through it.
 #[derive(Component)]
-struct Velocity(Vec2);
+struct CenterLine;
 
-#[derive(Bundle)]
-struct BallBundle {
-    ball: Ball,
-    shape: Shape,
-    velocity: Velocity,
-    position: Position
+#[derive(Component)]
+struct Player;
+
+#[derive(Component)]
+struct Ai;
+
+// How well the AI plays. Easier AIs move slower and aim worse. As a component
+// it overrides the chosen difficulty for just that AI paddle.
+#[derive(Resource, Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
 }
 
-impl BallBundle {
-    fn new(x: f32, y: f32) -> Self {
-        Self {
-            ball: Ball,
-            shape: Shape(Vec2::new(BALL_WIDTH, BALL_WIDTH)),
-            velocity: Velocity(Vec2::new(x, y)),
-            position: Position(Vec2::new(0., 0.))
+impl Difficulty {
+    // Scales the AI's top speed
+    fn speed_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.6,
+            Difficulty::Medium => 0.85,
+            Difficulty::Hard => 1.,
+        }
+    }
+
+    // Furthest the AI will line up from the ball, in pixels. Past half the
+    // paddle height it misses entirely.
+    fn max_aim_error(self) -> f32 {
+        match self {
+            Difficulty::Easy => 45.,
+            Difficulty::Medium => 25.,
+            Difficulty::Hard => 8.,
+        }
+    }
+
+    // Whether the AI works out where the ball will arrive, bounces and all,
+    // rather than just following it up and down
+    fn predicts_ball(self) -> bool {
+        self == Difficulty::Hard
+    }
+
+    // How long the AI sticks with where it decided to go before looking at
+    // the ball again
+    fn reaction_secs(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.25,
+            Difficulty::Medium => 0.15,
+            Difficulty::Hard => 0.05,
+        }
+    }
+
+    // How close the AI has to get to where it's going before it stops, in
+    // pixels, so it doesn't twitch back and forth over the exact spot
+    fn dead_zone(self) -> f32 {
+        match self {
+            Difficulty::Easy => 10.,
+            Difficulty::Medium => 6.,
+            Difficulty::Hard => 3.,
         }
     }
 }
 
-#[derive(Bundle)]
-struct PaddleBundle {
-    paddle: Paddle,
-    shape: Shape,
-    velocity: Velocity,
-    position: Position
+// The colors everything is drawn in. Balls and paddles pick theirs up when
+// they're spawned; the background and gutters change straight away.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum Theme {
+    #[default]
+    Classic,
+    Neon,
+    Monochrome,
 }
 
-impl PaddleBundle {
-    fn new(x: f32, y: f32) -> Self {
-        Self {
-            paddle: Paddle,
-            shape: Shape(Vec2::new(PADDLE_WIDTH, PADDLE_HEIGHT)),
-            velocity: Velocity(Vec2::new(0., 0.)),
-            position: Position(Vec2::new(x, y))
+impl Theme {
+    fn ball(self) -> Color {
+        match self {
+            Theme::Classic => Color::rgb(1., 0., 0.),
+            Theme::Neon => Color::rgb(1., 0.1, 0.8),
+            Theme::Monochrome => Color::WHITE,
+        }
+    }
+
+    fn right_paddle(self) -> Color {
+        match self {
+            Theme::Classic => Color::rgb(0., 1., 0.),
+            Theme::Neon => Color::rgb(0., 1., 1.),
+            Theme::Monochrome => Color::WHITE,
+        }
+    }
+
+    fn left_paddle(self) -> Color {
+        match self {
+            Theme::Classic => Color::rgb(0., 0., 1.),
+            Theme::Neon => Color::rgb(0.6, 1., 0.),
+            Theme::Monochrome => Color::WHITE,
+        }
+    }
+
+    fn gutter(self) -> Color {
+        match self {
+            Theme::Classic => Color::rgb(0., 0., 0.),
+            Theme::Neon => Color::rgb(0.3, 0., 0.5),
+            Theme::Monochrome => Color::rgb(0.3, 0.3, 0.3),
+        }
+    }
+
+    fn background(self) -> Color {
+        match self {
+            // Bevy's own default clear color, which the game started out on
+            Theme::Classic => Color::rgb(0.4, 0.4, 0.4),
+            Theme::Neon => Color::rgb(0.02, 0., 0.08),
+            Theme::Monochrome => Color::BLACK,
         }
     }
 }
 
-#[derive(Bundle)]
-struct GutterBundle {
-    gutter: Gutter,
-    shape: Shape,
-    position: Position,
+// Which paddle the player controls. The AI, the second player or the wall
+// gets the other side.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+struct PlayerSide(Side);
+
+impl Default for PlayerSide {
+    fn default() -> Self {
+        Self(Side::Right)
+    }
 }
 
-impl GutterBundle {
-    fn new(x: f32, y: f32, w: f32) -> Self {
-        Self {
-            gutter: Gutter,
-            shape: Shape(Vec2::new(w, GUTTER_HEIGHT)),
-            position: Position(Vec2::new(x, y)),
+// Where an AI paddle is lining up relative to the ball it's chasing. A new
+// error is picked each time a ball starts heading its way. The height it's
+// headed for is only worked out again once `reaction` runs out, however often
+// the game updates.
+#[derive(Component, Default)]
+struct AiAim {
+    offset: f32,
+    tracking: bool,
+    target_y: Option<f32>,
+    reaction: Timer,
+}
+
+impl AiAim {
+    // Whether it's time to look at the ball again, starting the wait for the
+    // next look if so
+    fn react(&mut self, delta: Duration, difficulty: Difficulty) -> bool {
+        if !self.reaction.tick(delta).finished() {
+            return false;
+        }
+        self.reaction = Timer::from_seconds(difficulty.reaction_secs(), TimerMode::Once);
+        true
+    }
+
+    // Where to line up for a ball at `ball_y`, missing it by the same error
+    // until the ball stops heading this way
+    fn aim_at(&mut self, ball_y: f32, difficulty: Difficulty, rng: &mut RngResource) -> f32 {
+        if !self.tracking {
+            let max_error = difficulty.max_aim_error();
+            self.offset = rng.0.gen_range(-max_error..=max_error);
+            self.tracking = true;
         }
+        ball_y + self.offset
+    }
+
+    fn lose_track(&mut self) {
+        self.tracking = false;
     }
 }
 
-#[derive(Default, States, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum GameState {
+// The second human player, on W and S, who takes the AI's paddle. With the
+// player on the right as usual, that's the left paddle.
+#[derive(Component)]
+struct PlayerTwo;
+
+// Who controls the left paddle. Picked on the main menu and applied when the
+// paddles are spawned.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum GameMode {
     #[default]
-    MainMenu,
-    // SettingsMenu,
-    // Playing,
+    VsAi,
+    // Two people at one keyboard, the player on the arrow keys and player two
+    // on W and S. There's no AI on the court at all.
+    TwoPlayer,
+    // On your own against a wall. Only your misses count.
+    Practice,
+    // Against a wall with a few lives and a ball that keeps getting faster
+    Survival,
+    // Against the AI, but whoever's ahead when time runs out wins
+    Timed,
+    // The AI against itself, over and over, with nobody at the controls
+    DemoMatch,
+    // Against the AI, with a second paddle each guarding a gutter. The
+    // gutters stop bouncing the ball back, so it can score through them too.
+    FourPaddles,
 }
 
+impl GameMode {
+    // Whether the left side of the court is a wall rather than a paddle
+    fn has_wall(self) -> bool {
+        matches!(self, GameMode::Practice | GameMode::Survival)
+    }
 
-fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .init_resource::<Score>()
-        .add_event::<Scored>()
-        .add_systems(
-            Startup,
-            (spawn_ball, spawn_camera, spawn_paddles, spawn_gutters, spawn_scoreboard),
-        )
-        .add_systems(
-            Update,
-            (
-                move_ball,
-                handle_player_input,
-                detect_scoring,
-                move_ai,
-                reset_ball.after(detect_scoring),
-                update_score.after(detect_scoring),
-                update_scoreboard.after(update_score),
-                move_paddles.after(handle_player_input),
-                project_positions.after(move_ball),
-                handle_collisions.after(move_ball),
-            ),
-        )
-        .run();
+    fn has_gutter_paddles(self) -> bool {
+        self == GameMode::FourPaddles
+    }
 }
 
-fn detect_scoring(
-    mut ball: Query<&mut Position, With<Ball>>,
-    window: Query<&Window>,
-    mut events: EventWriter<Scored>,
-) {
-    if let Ok(window) = window.get_single() {
-        let window_width = window.resolution.width();
-
-        if let Ok(ball) = ball.get_single_mut() {
-            // Here we write the events using our EventWriter
-            if ball.0.x > window_width / 2. {
-                events.send(Scored(Scorer::Ai));
-            } else if ball.0.x < -window_width / 2. {
-                events.send(Scored(Scorer::Player));
-            }
-        }
+// How many more misses the player can make in survival mode
+#[derive(Resource)]
+struct Lives(u32);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Self(STARTING_LIVES)
     }
 }
 
-fn update_score(mut score: ResMut<Score>, mut events: EventReader<Scored>) {
-    for event in events.read() {
-        match event.0 {
-            Scorer::Ai => score.ai += 1,
-            Scorer::Player => score.player += 1,
-        }
+// The time left in a timed match
+#[derive(Resource)]
+struct MatchTimer(Timer);
+
+impl Default for MatchTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(MATCH_SECS, TimerMode::Once))
     }
+}
+
+// Shows the time left at the top of the screen in timed mode
+#[derive(Component)]
+struct MatchTimerText;
+
+// Shows how many points in a row a side has won, under that side's score
+#[derive(Component)]
+struct StreakText(Side);
+
+// One of the balls in the corner showing how many lives are left. The number
+// is which life it stands for, counting from zero.
+#[derive(Component)]
+struct LifeIcon(u32);
 
-    println!("Score: {} - {}", score.player, score.ai);
+// Sent by `detect_scoring` for every point, where `side` is who won it.
+// `update_score` adds it to the `Score`, and once that's done the scoreboard,
+// `check_win` and `count_rally` read it too. `reset_ball` serves again from
+// it, `play_score_sound` plays the jingle and `lose_lives` counts misses in
+// survival mode.
+#[derive(Event)]
+struct ScoredEvent {
+    side: Side,
 }
 
-fn update_scoreboard(
-    mut player_score: Query<&mut Text, With<PlayerScoreboard>>,
-    mut ai_score: Query<&mut Text, (With<AiScoreboard>, Without<PlayerScoreboard>)>,
-    score: Res<Score>,
-) {
-    if score.is_changed() {
-        if let Ok(mut player_score) = player_score.get_single_mut() {
-            player_score.sections[0].value = score.player.to_string();
-        }
+// Sent by `handle_collisions` when the ball runs into something, where
+// `entity` is the thing it hit. `apply_ball_bounce` turns the ball around in
+// response, and anything else that cares about hits can read these too.
+#[derive(Event)]
+struct BallCollisionEvent {
+    ball: Entity,
+    entity: Entity,
+    collision: Collision,
+}
 
-        if let Ok(mut ai_score) = ai_score.get_single_mut() {
-            ai_score.sections[0].value = score.ai.to_string();
-        }
-    }
+// What the ball bounced off
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bounce {
+    // Returned by the face of a paddle
+    Paddle,
+    // Glanced off a gutter or the end of a paddle
+    Wall,
 }
 
-fn handle_collisions(
-    mut ball: Query<(&mut Velocity, &Position, &Shape), With<Ball>>,
-    // We can collide with anything else that has a shape and position that is
-    // not itself a ball
-    other_things: Query<(&Position, &Shape), Without<Ball>>,
-) {
-    if let Ok((mut ball_velocity, ball_position, ball_shape)) = ball.get_single_mut() {
-        for (position, shape) in &other_things {
-            if let Some(collision) = collide(
-                ball_position.0.extend(0.),
-                ball_shape.0,
-                position.0.extend(0.),
-                shape.0,
-            ) {
-                match collision {
-                    Collision::Left => {
-                        ball_velocity.0.x *= -1.;
-                    }
-                    Collision::Right => {
-                        ball_velocity.0.x *= -1.;
-                    }
-                    Collision::Top => {
-                        ball_velocity.0.y *= -1.;
-                    }
-                    Collision::Bottom => {
-                        ball_velocity.0.y *= -1.;
-                    }
-                    Collision::Inside => {
-                        // Do nothing
-                    }
-                }
+impl Bounce {
+    // `paddle` is the shape of the paddle hit, if it was one. The face of a
+    // paddle is its long side, so the end paddles return the ball off their
+    // left and right and the gutter paddles off their top and bottom.
+    fn new(collision: Collision, paddle: Option<&Shape>) -> Self {
+        let face = match paddle {
+            Some(shape) if shape.0.x > shape.0.y => {
+                matches!(collision, Collision::Top | Collision::Bottom)
             }
+            Some(_) => matches!(collision, Collision::Left | Collision::Right),
+            None => false,
+        };
+        if face {
+            Bounce::Paddle
+        } else {
+            Bounce::Wall
         }
     }
 }
 
-fn handle_player_input(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut paddle: Query<(&mut Velocity, &Paddle), With<Player>>,
-) {
-    if let Ok((mut velocity, _)) = paddle.get_single_mut() {
-        if keyboard_input.pressed(KeyCode::Up) {
-            velocity.0.y = PADDLE_SPEED;
-        } else if keyboard_input.pressed(KeyCode::Down) {
-            velocity.0.y = -PADDLE_SPEED;
-        } else {
-            velocity.0.y = 0.;
-        }
+#[derive(Resource)]
+struct Sounds {
+    paddle_hit: Handle<AudioSource>,
+    wall_hit: Handle<AudioSource>,
+    score: Handle<AudioSource>,
+}
+
+// How loud every sound is, from silent at 0 up to full volume at 1
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct Volume(f32);
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self(1.)
     }
 }
 
-fn move_ai(
-    mut ai: Query<(&mut Velocity, &Position), With<Ai>>,
-    ball: Query<&Position, With<Ball>>,
-) {
-    if let Ok((mut velocity, position)) = ai.get_single_mut() {
-        if let Ok(ball_position) = ball.get_single() {
-            let a_to_b = ball_position.0 - position.0;
-            velocity.0.y = a_to_b.y.signum();
-        }
+impl Volume {
+    // One notch louder or quieter. Rounding to whole tenths means stepping
+    // all the way down lands exactly on silent.
+    fn step(self, direction: f32) -> Self {
+        Self(((self.0 + direction * 0.1) * 10.).round().clamp(0., 10.) / 10.)
+    }
+
+    fn is_muted(self) -> bool {
+        self.0 <= 0.
     }
 }
 
+// Whether the ball is gently pulled toward the player's paddle on its way
+// there, to make it easier to return. Off by default since it's unfair on
+// the other side.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+struct AssistMode(bool);
+
+// The score for each side of the court. The player's paddle is on whichever
+// side `PlayerSide` says, and the AI, the second player or the wall has the
+// other.
+#[derive(Resource, Default)]
+pub struct Score {
+    pub left: u32,
+    pub right: u32,
+}
 
-fn move_ball(mut ball: Query<(&mut Position, &Velocity), With<Ball>>) {
-    if let Ok((mut position, velocity)) = ball.get_single_mut() {
-        position.0 += velocity.0 * BALL_SPEED;
+impl Score {
+    fn of(&self, side: Side) -> u32 {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+        }
     }
 }
 
-fn reset_ball(
-    mut ball: Query<(&mut Position, &mut Velocity), With<Ball>>,
-    mut events: EventReader<Scored>,
-) {
-    // Here we read the events using an EventReader
-    for event in events.read() {
-        if let Ok((mut position, mut velocity)) = ball.get_single_mut() {
-            match event.0 {
-                Scorer::Ai => {
-                    position.0 = Vec2::new(0., 0.);
-                    velocity.0 = Vec2::new(-1., 1.);
-                }
-                Scorer::Player => {
-                    position.0 = Vec2::new(0., 0.);
-                    velocity.0 = Vec2::new(1., 1.);
-                }
-            }
+// How the current match is going, for the game over screen: rally lengths
+// and the total, counted in paddle hits, how many points in a row the last
+// side to score has won, how long it's been on, and the fastest the ball has
+// gone (in pixels per second)
+#[derive(Resource, Default)]
+struct MatchStats {
+    current_rally: u32,
+    longest_rally: u32,
+    paddle_hits: u32,
+    streak: Option<(Side, u32)>,
+    match_secs: f32,
+    top_speed: f32,
+}
+
+impl MatchStats {
+    // Returns how many points in a row `side` has now won
+    fn count_point(&mut self, side: Side) -> u32 {
+        let streak = match self.streak {
+            Some((streak_side, points)) if streak_side == side => points + 1,
+            _ => 1,
+        };
+        self.streak = Some((side, streak));
+        streak
+    }
+
+    fn streak_of(&self, side: Side) -> u32 {
+        match self.streak {
+            Some((streak_side, points)) if streak_side == side => points,
+            _ => 0,
         }
     }
+
+    fn end_rally(&mut self) {
+        self.longest_rally = self.longest_rally.max(self.current_rally);
+        self.current_rally = 0;
+    }
 }
 
-fn move_paddles(
-    mut paddle: Query<(&mut Position, &Velocity), With<Paddle>>,
-    window: Query<&Window>,
-) {
-    if let Ok(window) = window.get_single() {
-        let window_height = window.resolution.height();
+// Gameplay tuning. These can be changed at runtime; spawn systems read them
+// each time the court is set up.
+#[derive(Resource)]
+struct GameConfig {
+    // Speeds are in pixels per second
+    ball_speed: f32,
+    paddle_speed: f32,
+    paddle_width: f32,
+    paddle_height: f32,
+    gutter_height: f32,
+    // How much faster the ball gets each time a paddle returns it, and the
+    // most it can speed up to over a rally
+    rally_speed_up: f32,
+    max_ball_speed: f32,
+    // Top speed of the AI paddle as a fraction of the paddle speed
+    ai_speed: f32,
+    // How many seconds each dot of the ball's trail takes to fade, which sets
+    // how long the trail is. Zero turns the trail off.
+    trail_length: f32,
+    // How much of a paddle's vertical movement is passed on to the ball when
+    // it's hit, relative to the ball's own direction
+    paddle_spin: f32,
+    // Steepest angle (in radians from horizontal) a serve can start at. Zero
+    // serves dead straight every time.
+    serve_spread: f32,
+    // How fast the game runs during a rally either side could win the match
+    // on, as a fraction of normal speed. One turns the slow motion off.
+    match_point_time_scale: f32,
+    // Whether balls cast a shadow
+    ball_shadow: bool,
+    // How quickly paddles get up to speed and slow back down, in full paddle
+    // speeds per second
+    paddle_acceleration: f32,
+    // Which side's paddle is shrunk to even out a match, if either
+    handicap: Option<Side>,
+    // Whether practice mode shows where the ball is headed, and in what color
+    show_ball_path: bool,
+    ball_path_color: Color,
+    // Whether a match paused by switching to another window carries on by
+    // itself when you switch back
+    resume_on_focus: bool,
+    // How much faster the ball gets every second in survival mode, up to the
+    // usual top speed
+    survival_speed_up: f32,
+    // How many particles burst out when the ball hits a paddle. Zero turns
+    // them off.
+    impact_particles: usize,
+    // Whether balls bounce off each other when there's more than one
+    balls_collide: bool,
+    // Whether power-ups turn up on the court now and then
+    power_ups: bool,
+    // The color the ball heats up to as a rally gets faster, starting from
+    // the theme's ball color at serving speed
+    ball_fast_color: Color,
+    // How many times a second the simulation steps. Speeds don't depend on
+    // it, but collisions are only checked once a step.
+    target_fps: f64,
+    // How many seconds a tap of the up or down key keeps counting after it's
+    // let go, so a tap between two steps still moves the paddle. Zero turns
+    // the buffer off.
+    input_buffer_secs: f32,
+    // Whether a moving paddle puts spin on the ball that curves it through
+    // the air. Off for classic Pong.
+    curve_ball: bool,
+    // How many seconds the screen takes to fade to black when moving between
+    // menus and the game, and the same again to fade back. Zero cuts straight
+    // over.
+    fade_secs: f32,
+    // How many seconds the ball sits still at the start of a match before
+    // the serve countdown begins
+    start_delay_secs: f32,
+    // The shape the court keeps whatever the shape of the window, as width
+    // by height (4 by 3, say). The rest of the window is covered by bars.
+    // Zero stretches the court to fill the window.
+    court_aspect: Vec2,
+    // Whether whoever lost the last point holds the ball on their paddle and
+    // launches it when they're ready, rather than it being served from the
+    // middle after a countdown
+    serve_from_paddle: bool,
+    // How much of its speed the ball keeps each time it comes off a paddle.
+    // Under one feels heavier, over one bouncier. It scales the speed after
+    // the rally speed-up is added, and the top speed still caps the result.
+    restitution: f32,
+    // How wide the ball is, in pixels. The circle that's drawn and the box
+    // collisions are checked against are both this size.
+    ball_size: f32,
+    // Points needed to win a match, and whether the winner also has to be
+    // two points clear. With that on, a match at the target carries on
+    // until somebody pulls ahead.
+    win_target: u32,
+    win_by_two: bool,
+    // Whether the camera slowly closes in on the court during a rally either
+    // side could win the match on
+    match_point_zoom: bool,
+}
 
-        for (mut position, velocity) in &mut paddle {
-            let new_position = position.0 + velocity.0 * PADDLE_SPEED;
-            if new_position.y.abs() < window_height / 2. - GUTTER_HEIGHT - PADDLE_HEIGHT / 2. {
-                position.0 = new_position;
-            }
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            ball_speed: 300.,
+            paddle_speed: 300.,
+            paddle_width: 10.,
+            paddle_height: 50.,
+            gutter_height: 20.,
+            rally_speed_up: 15.,
+            max_ball_speed: 600.,
+            ai_speed: 1.,
+            trail_length: 0.15,
+            paddle_spin: 0.3,
+            serve_spread: 0.45,
+            match_point_time_scale: 0.7,
+            ball_shadow: true,
+            paddle_acceleration: 10.,
+            handicap: None,
+            show_ball_path: true,
+            ball_path_color: Color::rgba(1., 1., 1., 0.3),
+            resume_on_focus: false,
+            survival_speed_up: 3.,
+            impact_particles: 8,
+            balls_collide: false,
+            power_ups: true,
+            ball_fast_color: Color::WHITE,
+            target_fps: 60.,
+            input_buffer_secs: 0.,
+            curve_ball: false,
+            fade_secs: 0.2,
+            start_delay_secs: 0.,
+            serve_from_paddle: false,
+            court_aspect: Vec2::new(4., 3.),
+            restitution: 1.,
+            ball_size: BALL_WIDTH,
+            win_target: WINNING_SCORE,
+            win_by_two: false,
+            match_point_zoom: true,
         }
     }
 }
 
-fn project_positions(mut ball: Query<(&mut Transform, &Position)>) {
-    for (mut transform, position) in &mut ball {
-        transform.translation = position.0.extend(0.);
+impl GameConfig {
+    // Whether a side with `points` has won against one with `other_points`
+    fn has_won(&self, points: u32, other_points: u32) -> bool {
+        points >= self.win_target && (!self.win_by_two || points >= other_points + 2)
+    }
+
+    fn paddle_size(&self, side: Side) -> Vec2 {
+        let scale = if self.handicap == Some(side) {
+            HANDICAP_SCALE
+        } else {
+            1.
+        };
+        Vec2::new(self.paddle_width, self.paddle_height * scale)
     }
 }
 
-fn spawn_ball(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    println!("Spawning ball...");
+// How fast the ball and paddles move compared to normal. Only gameplay reads
+// this, so menus and the UI always run at full speed.
+#[derive(Resource)]
+struct TimeScale(f32);
 
-    let mesh = Mesh::from(shape::Circle::new(BALL_WIDTH / 2.0));
-    let material = ColorMaterial::from(BALL_COLOR);
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
 
-    // Now our mesh shape is derived from the `Shape` we made as a new component
-    let mesh_handle = meshes.add(mesh);
-    let material_handle = materials.add(material);
+// How big the court is. `track_court_size` keeps it as big as fits in the
+// window at the configured aspect ratio, but without a window (running
+// headless) it can be set directly instead and the court is laid out to fit.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct CourtDimensions {
+    width: f32,
+    height: f32,
+}
 
-    commands.spawn((
-        BallBundle::new(1., 0.),
-        MaterialMesh2dBundle {
-            mesh: mesh_handle.into(),
-            material: material_handle,
-            ..default()
-        },
-    ));
+impl Default for CourtDimensions {
+    fn default() -> Self {
+        let window = WindowSettings::default();
+        Self {
+            width: window.width,
+            height: window.height,
+        }
+    }
 }
 
-fn spawn_paddles(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+impl CourtDimensions {
+    fn size(self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    // The biggest court of the shape `aspect` (width by height) that fits in
+    // `window`, centered in it. A zero `aspect` fills the whole window.
+    fn fit(window: Vec2, aspect: Vec2) -> Self {
+        let (mut width, mut height) = (window.x, window.y);
+        if aspect.x > 0. && aspect.y > 0. {
+            // Compared by cross-multiplying, so a window that's already the
+            // right shape is left exactly as it is
+            if window.x * aspect.y > window.y * aspect.x {
+                width = window.y * aspect.x / aspect.y;
+            } else if window.x * aspect.y < window.y * aspect.x {
+                height = window.x * aspect.y / aspect.x;
+            }
+        }
+        Self { width, height }
+    }
+}
+
+fn track_court_size(
     window: Query<&Window>,
+    config: Res<GameConfig>,
+    mut court: ResMut<CourtDimensions>,
 ) {
-    println!("Spawning paddles...");
-
     if let Ok(window) = window.get_single() {
-        let window_width = window.resolution.width();
-        // right and left of the screen with a bit of padding
-        let padding = 50.;
-        let right_paddle_x = window_width / 2. - padding;
-        let left_paddle_x = -window_width / 2. + padding;
-
-        let mesh = Mesh::from(shape::Quad::new(Vec2::new(
-            PADDLE_WIDTH,
-            PADDLE_HEIGHT,
-        )));
-
-        let mesh_handle = meshes.add(mesh);
+        let window_size = Vec2::new(window.resolution.width(), window.resolution.height());
+        // Only a real change should count, since resizing the court moves
+        // everything around
+        court.set_if_neq(CourtDimensions::fit(window_size, config.court_aspect));
+    }
+}
 
-        commands.spawn((
-            Player,
-            PaddleBundle::new(right_paddle_x, 0.),
-            MaterialMesh2dBundle {
-                mesh: mesh_handle.clone().into(),
-                material: materials.add(ColorMaterial::from(Color::rgb(0., 1., 0.))),
-                ..default()
-            },
-        ));
+// One of the bars covering the window outside the court, on the side of it
+// given by the direction
+#[derive(Component)]
+struct LetterboxBar(Vec2);
 
+// A bar on each side of the court. Whichever aren't needed sit just off the
+// edge of the window.
+fn spawn_letterbox_bars(mut commands: Commands) {
+    for direction in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
         commands.spawn((
-            // Adding the Ai component here so we can query for this specific
-            // paddle in our `move_ai` system
-            Ai,
-            PaddleBundle::new(left_paddle_x, 0.),
-            MaterialMesh2dBundle {
-                mesh: mesh_handle.into(),
-                material: materials.add(ColorMaterial::from(Color::rgb(0., 0., 1.))),
+            LetterboxBar(direction),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK,
+                    ..default()
+                },
                 ..default()
             },
         ));
     }
 }
 
-fn spawn_gutters(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+fn place_letterbox_bars(
     window: Query<&Window>,
+    court: Res<CourtDimensions>,
+    mut bars: Query<(&mut Sprite, &mut Transform, &LetterboxBar)>,
 ) {
-    println!("Spawning gutters...");
-
-    if let Ok(window) = window.get_single() {
-        let window_width = window.resolution.width();
-        let window_height = window.resolution.height();
-        let top_gutter_y = window_height / 2. - GUTTER_HEIGHT / 2.;
-        let bottom_gutter_y = -window_height / 2. + GUTTER_HEIGHT / 2.;
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.resolution.width(), window.resolution.height());
 
-        let top_gutter = GutterBundle::new(0., top_gutter_y, window_width);
-        let bottom_gutter = GutterBundle::new(0., bottom_gutter_y, window_width);
-        let mesh = meshes.add(Mesh::from(shape::Quad::new(top_gutter.shape.0)));
-        let material = materials.add(ColorMaterial::from(Color::rgb(0., 0., 0.)));
+    for (mut sprite, mut transform, bar) in &mut bars {
+        // Each bar is as big as the whole window, so there's no gap left
+        // around it when the screen shakes. Its inner edge lines up with the
+        // edge of the court.
+        sprite.custom_size = Some(window_size);
+        let offset = (court.size() + window_size) / 2. * bar.0;
+        transform.translation = offset.extend(LETTERBOX_Z);
+    }
+}
 
-        commands.spawn((
-            top_gutter,
-            MaterialMesh2dBundle {
-                mesh: mesh.clone().into(),
-                material: material.clone(),
-                ..default()
-            },
-        ));
+// Counts down before each serve. The ball waits at the center until it's done.
+#[derive(Resource)]
+struct ServeCountdown(Timer);
 
-        commands.spawn((
-            bottom_gutter,
-            MaterialMesh2dBundle {
-                mesh: mesh.clone().into(),
-                material: material.clone(),
-                ..default()
-            },
-        ));
+impl Default for ServeCountdown {
+    fn default() -> Self {
+        Self(Timer::from_seconds(SERVE_COUNTDOWN_SECS, TimerMode::Once))
     }
 }
 
-fn spawn_scoreboard(
+// Everything that only exists during a match: balls, paddles, effects and the
+// in-game text. The court itself (gutters, center line and scores) stays up
+// behind the menus.
+#[derive(Component)]
+struct Gameplay;
+
+// Where every random choice that affects play comes from: serves, the AI's
+// aim and where power-ups turn up. Seeding it makes a run play out the same
+// way every time. Purely cosmetic randomness (particles, screen shake) comes
+// from `CosmeticRng`, so turning the effects on or off doesn't change the game.
+#[derive(Resource)]
+struct RngResource(StdRng);
+
+impl Default for RngResource {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+impl RngResource {
+    fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+// Where the randomness that's only for show comes from: impact particles,
+// screen shake and the ball bouncing around behind the main menu. It's seeded
+// along with `RngResource`, so a seeded run looks the same every time too.
+#[derive(Resource)]
+struct CosmeticRng(StdRng);
+
+impl Default for CosmeticRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+impl CosmeticRng {
+    // A different stream from the gameplay generator with the same seed, so
+    // the two don't draw matching numbers
+    fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(!seed))
+    }
+}
+
+// Holds off the serve countdown at the start of a match, so there's a moment
+// to get ready before it starts. Later serves don't wait for it.
+#[derive(Resource, Default)]
+struct StartDelay(Timer);
+
+#[derive(Component)]
+struct CountdownText;
+
+// Points the way the ball is about to be served while the countdown runs
+#[derive(Component)]
+struct ServeArrow;
+
+// Shakes the camera for a moment, easing off as the timer runs out
+#[derive(Resource, Default)]
+struct ScreenShake {
+    magnitude: f32,
+    timer: Timer,
+}
+
+// A dot left behind the ball that fades out and then despawns
+#[derive(Component)]
+struct TrailFade(Timer);
+
+// A speck thrown off when the ball hits a paddle. It flies off at its own
+// velocity (in pixels per second) and fades out over its lifetime.
+#[derive(Component)]
+struct Particle(Vec2);
+
+#[derive(Component)]
+struct ParticleLifetime(Timer);
+
+// What a power-up does to the ball that picks it up
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PowerUpKind {
+    Big,
+    Small,
+    Fast,
+    Slow,
+}
+
+const POWER_UP_KINDS: [PowerUpKind; 4] = [
+    PowerUpKind::Big,
+    PowerUpKind::Small,
+    PowerUpKind::Fast,
+    PowerUpKind::Slow,
+];
+
+impl PowerUpKind {
+    // How big the ball gets, compared to its usual size
+    fn ball_scale(self) -> f32 {
+        match self {
+            PowerUpKind::Big => 2.,
+            PowerUpKind::Small => 0.5,
+            PowerUpKind::Fast | PowerUpKind::Slow => 1.,
+        }
+    }
+
+    // How fast the ball goes, compared to its rally speed
+    fn speed_scale(self) -> f32 {
+        match self {
+            PowerUpKind::Fast => 1.5,
+            PowerUpKind::Slow => 0.6,
+            PowerUpKind::Big | PowerUpKind::Small => 1.,
+        }
+    }
+
+    // The pickup is drawn in this color, and so is the ball while it has the
+    // power-up so players can tell what it's doing
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::Big => Color::rgb(1., 0.8, 0.),
+            PowerUpKind::Small => Color::rgb(0.4, 0.8, 1.),
+            PowerUpKind::Fast => Color::rgb(1., 0.3, 0.1),
+            PowerUpKind::Slow => Color::rgb(0.3, 1., 0.5),
+        }
+    }
+}
+
+// A pickup floating on the court, waiting for a ball to run through it
+#[derive(Component)]
+struct PowerUp {
+    kind: PowerUpKind,
+}
+
+// The power-up a ball has picked up, and how much longer it keeps it. A ball
+// only has one at a time: picking up another replaces it.
+#[derive(Component)]
+struct ActivePowerUp {
+    kind: PowerUpKind,
+    timer: Timer,
+}
+
+// Counts down to the next power-up appearing
+#[derive(Resource)]
+struct PowerUpSpawner(Timer);
+
+impl Default for PowerUpSpawner {
+    fn default() -> Self {
+        Self(Timer::from_seconds(POWER_UP_INTERVAL_SECS, TimerMode::Repeating))
+    }
+}
+
+// What the player on the right paddle steers it with
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum InputMode {
+    #[default]
+    Keyboard,
+    // The first connected gamepad's D-pad or left stick
+    Gamepad,
+    // The paddle follows the cursor up and down
+    Mouse,
+}
+
+// Something the player can do with a key
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum InputAction {
+    Up,
+    Down,
+    // Sliding the player's gutter paddle in four paddle mode
+    Left,
+    Right,
+    Pause,
+}
+
+const INPUT_ACTIONS: [InputAction; 5] = [
+    InputAction::Up,
+    InputAction::Down,
+    InputAction::Left,
+    InputAction::Right,
+    InputAction::Pause,
+];
+
+// Which key does what for the player on the right paddle
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+struct InputBindings {
+    up: KeyCode,
+    down: KeyCode,
+    left: KeyCode,
+    right: KeyCode,
+    pause: KeyCode,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::Up,
+            down: KeyCode::Down,
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            pause: KeyCode::Escape,
+        }
+    }
+}
+
+impl InputBindings {
+    fn key(&self, action: InputAction) -> KeyCode {
+        match action {
+            InputAction::Up => self.up,
+            InputAction::Down => self.down,
+            InputAction::Left => self.left,
+            InputAction::Right => self.right,
+            InputAction::Pause => self.pause,
+        }
+    }
+
+    fn key_mut(&mut self, action: InputAction) -> &mut KeyCode {
+        match action {
+            InputAction::Up => &mut self.up,
+            InputAction::Down => &mut self.down,
+            InputAction::Left => &mut self.left,
+            InputAction::Right => &mut self.right,
+            InputAction::Pause => &mut self.pause,
+        }
+    }
+
+    // Puts `action` on `key`. Whatever was already on that key swaps over to
+    // the old key, so one key never ends up doing two things.
+    fn rebind(&mut self, action: InputAction, key: KeyCode) {
+        let old_key = self.key(action);
+        for other in INPUT_ACTIONS {
+            if other != action && self.key(other) == key {
+                *self.key_mut(other) = old_key;
+            }
+        }
+        *self.key_mut(action) = key;
+    }
+}
+
+// Where each ball and paddle was at one fixed update step
+type ReplayFrame = Vec<(Entity, Vec2)>;
+
+// Every step of the latest point, so it can be watched again once it's over.
+// Starts over each time the ball is served.
+#[derive(Resource, Default)]
+struct ReplayBuffer {
+    frames: VecDeque<ReplayFrame>,
+    recording: bool,
+}
+
+// Set while the last point is being replayed. Nothing else moves until it's
+// done, and then everything goes back to where it was.
+#[derive(Resource, Default)]
+struct ReplayMode(Option<Replay>);
+
+struct Replay {
+    // How far through the buffer we are, in steps. It goes up by less than
+    // one a step so the replay plays in slow motion.
+    frame: f32,
+    resume: ReplayFrame,
+}
+
+// Whether the match is paused. The scene is still drawn while paused, but
+// nothing moves.
+#[derive(Resource, Default)]
+struct Paused(bool);
+
+// How the last match ended, for the game over screen to read. A timed match
+// can end level, in which case it's a draw.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+enum Winner {
+    Left,
+    Right,
+    Draw,
+}
+
+impl From<Side> for Winner {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Left => Winner::Left,
+            Side::Right => Winner::Right,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Position(Vec2);
+
+// Where an entity was before the latest fixed update step moved it
+#[derive(Component)]
+struct PreviousPosition(Vec2);
+
+#[derive(Component)]
+struct Shape(Vec2);
+
+#[derive(Component)]
+struct Velocity(Vec2);
+
+// The velocity a paddle's controls are asking for. `move_paddles` speeds the
+// paddle up or slows it down toward this rather than jumping straight to it.
+#[derive(Component)]
+struct TargetVelocity(Vec2);
+
+// How near the front `project_positions` draws something, one of the `_Z`
+// constants. Things without one keep whatever depth they were spawned at.
+#[derive(Component, Clone, Copy)]
+struct ZLayer(f32);
+
+// What kind of thing this is as far as collisions go. Balls only run into
+// things in layers they collide with, and nothing without a layer at all.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum CollisionLayer {
+    Ball,
+    Paddle,
+    Wall,
+}
+
+impl CollisionLayer {
+    fn collides_with(self, other: CollisionLayer, config: &GameConfig) -> bool {
+        match (self, other) {
+            (CollisionLayer::Ball, CollisionLayer::Ball) => config.balls_collide,
+            _ => true,
+        }
+    }
+}
+
+// How fast the ball is currently moving. It starts at the configured ball
+// speed and picks up with every paddle hit until a point is scored.
+#[derive(Component)]
+struct RallySpeed(f32);
+
+// How fast a ball's heading is turning, in radians per second, counter-
+// clockwise. Paddles only put spin on the ball when `curve_ball` is on.
+#[derive(Component, Default)]
+struct Spin(f32);
+
+#[derive(Bundle)]
+struct BallBundle {
+    ball: Ball,
+    shape: Shape,
+    velocity: Velocity,
+    position: Position,
+    previous_position: PreviousPosition,
+    rally_speed: RallySpeed,
+    spin: Spin,
+    layer: CollisionLayer,
+    z_layer: ZLayer,
+}
+
+impl BallBundle {
+    // Balls start out still, and get going once they're served
+    fn new(speed: f32, size: f32) -> Self {
+        Self {
+            ball: Ball,
+            shape: Shape(Vec2::splat(size)),
+            velocity: Velocity(Vec2::ZERO),
+            position: Position(Vec2::new(0., 0.)),
+            previous_position: PreviousPosition(Vec2::new(0., 0.)),
+            rally_speed: RallySpeed(speed),
+            spin: Spin(0.),
+            layer: CollisionLayer::Ball,
+            z_layer: ZLayer(BALL_Z),
+        }
+    }
+}
+
+#[derive(Bundle)]
+struct PaddleBundle {
+    paddle: Paddle,
+    side: Side,
+    shape: Shape,
+    velocity: Velocity,
+    target_velocity: TargetVelocity,
+    position: Position,
+    previous_position: PreviousPosition,
+    layer: CollisionLayer,
+    z_layer: ZLayer,
+}
+
+impl PaddleBundle {
+    fn new(side: Side, x: f32, y: f32, size: Vec2) -> Self {
+        Self {
+            paddle: Paddle,
+            side,
+            shape: Shape(size),
+            velocity: Velocity(Vec2::new(0., 0.)),
+            target_velocity: TargetVelocity(Vec2::new(0., 0.)),
+            position: Position(Vec2::new(x, y)),
+            previous_position: PreviousPosition(Vec2::new(x, y)),
+            layer: CollisionLayer::Paddle,
+            z_layer: ZLayer(PADDLE_Z),
+        }
+    }
+}
+
+#[derive(Bundle)]
+struct GutterBundle {
+    gutter: Gutter,
+    shape: Shape,
+    position: Position,
+    layer: CollisionLayer,
+    z_layer: ZLayer,
+}
+
+impl GutterBundle {
+    fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self {
+            gutter: Gutter,
+            shape: Shape(Vec2::new(w, h)),
+            position: Position(Vec2::new(x, y)),
+            layer: CollisionLayer::Wall,
+            z_layer: ZLayer(GUTTER_Z),
+        }
+    }
+}
+
+#[derive(Default, States, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum GameState {
+    #[default]
+    MainMenu,
+    SettingsMenu,
+    Playing,
+    GameOver,
+}
+
+
+// The window the game opens in. `main` reads overrides from the environment
+// before the app is built; the court is laid out to fit whatever size it gets.
+#[derive(Resource, Clone)]
+struct WindowSettings {
+    title: String,
+    width: f32,
+    height: f32,
+    // Whether drawing waits for the display, capping the frame rate at its
+    // refresh rate. The simulation runs at the same speed either way. Can be
+    // switched from the settings menu while the game's running.
+    vsync: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: "Bevy Pong".to_string(),
+            width: 800.,
+            height: 600.,
+            vsync: true,
+        }
+    }
+}
+
+fn present_mode(vsync: bool) -> PresentMode {
+    if vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    }
+}
+
+impl WindowSettings {
+    // The defaults, with any of PONG_WIDTH, PONG_HEIGHT and PONG_VSYNC (true or
+    // false) that are set taking their place. Values that don't parse are
+    // ignored.
+    fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            width: var("PONG_WIDTH").unwrap_or(defaults.width),
+            height: var("PONG_HEIGHT").unwrap_or(defaults.height),
+            vsync: var("PONG_VSYNC").unwrap_or(defaults.vsync),
+            ..defaults
+        }
+    }
+
+    fn window(&self) -> Window {
+        Window {
+            title: self.title.clone(),
+            resolution: (self.width, self.height).into(),
+            present_mode: present_mode(self.vsync),
+            ..default()
+        }
+    }
+}
+
+fn main() {
+    let window_settings = WindowSettings::from_env();
+    // Setting PONG_SEED to a number makes the serves, the AI and the power-ups
+    // the same every run, and the effects too
+    let seed = std::env::var("PONG_SEED").ok().and_then(|seed| seed.parse().ok());
+    let rng = seed.map_or_else(RngResource::default, RngResource::seeded);
+    let cosmetic_rng = seed.map_or_else(CosmeticRng::default, CosmeticRng::seeded);
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(window_settings.window()),
+                ..default()
+            }),
+            GamePlugin,
+            menu::MenuPlugins,
+            save::SavePlugin,
+            debug::DebugPlugin,
+        ))
+        .insert_resource(window_settings)
+        .insert_resource(rng)
+        .insert_resource(cosmetic_rng)
+        .run();
+}
+
+// Everything needed to play a match: the game states, gameplay resources and
+// events, and the systems that set up the court and run it. Menus, saving and
+// debugging tools are separate plugins layered on top.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .init_resource::<GameConfig>()
+            .init_resource::<WindowSettings>()
+            .init_resource::<CourtDimensions>()
+            .init_resource::<Score>()
+            .init_resource::<Paused>()
+            .init_resource::<GameMode>()
+            .init_resource::<Difficulty>()
+            .init_resource::<ServeCountdown>()
+            .init_resource::<StartDelay>()
+            .init_resource::<RngResource>()
+            .init_resource::<CosmeticRng>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<TimeScale>()
+            .init_resource::<InputBindings>()
+            .init_resource::<InputMode>()
+            .init_resource::<PlayerSide>()
+            .init_resource::<AssistMode>()
+            .init_resource::<Theme>()
+            .init_resource::<MatchStats>()
+            .init_resource::<ReplayBuffer>()
+            .init_resource::<ReplayMode>()
+            .init_resource::<Lives>()
+            .init_resource::<Volume>()
+            .init_resource::<PowerUpSpawner>()
+            .init_resource::<MatchTimer>()
+            .add_event::<ScoredEvent>()
+            .add_event::<BallCollisionEvent>()
+            .add_systems(
+                Startup,
+                (
+                    load_sounds,
+                    spawn_camera,
+                    spawn_letterbox_bars,
+                    spawn_gutters,
+                    spawn_center_line,
+                    spawn_scoreboard,
+                ),
+            )
+            // Each time we enter play the match starts over from a fresh ball,
+            // fresh paddles and a zeroed score
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    reset_score,
+                    update_scoreboard.after(reset_score),
+                    reset_stats,
+                    reset_time_scale,
+                    reset_replay,
+                    unpause,
+                    start_serve_countdown,
+                    reset_start_delay,
+                    spawn_ball,
+                    spawn_paddles,
+                    spawn_score_walls,
+                    spawn_countdown_text,
+                    spawn_ball_path,
+                    reset_lives,
+                    spawn_life_icons,
+                    reset_power_ups,
+                    reset_match_timer,
+                    spawn_match_timer_text,
+                    spawn_streak_text,
+                ),
+            )
+            .add_systems(
+                OnExit(GameState::Playing),
+                (cleanup_gameplay, reset_camera_zoom),
+            )
+            .add_systems(OnExit(GameState::GameOver), cleanup_gameplay)
+            // The simulation steps at a fixed rate so the game plays the same
+            // however fast it's drawn, with or without vsync
+            .add_systems(PreUpdate, apply_target_fps.run_if(resource_changed::<GameConfig>()))
+            .add_systems(PreUpdate, apply_vsync.run_if(resource_changed::<WindowSettings>()))
+            // The court's size is picked up before anything is spawned on it,
+            // and again each frame in case the window's been resized
+            .add_systems(PreStartup, track_court_size)
+            .add_systems(PreUpdate, track_court_size)
+            .add_systems(
+                FixedUpdate,
+                (
+                    move_ball.run_if(serve_countdown_finished),
+                    assist_ball.before(move_ball).run_if(serve_countdown_finished),
+                    move_paddles,
+                    hold_ball.after(move_ball).after(move_paddles),
+                    handle_collisions.after(move_ball).after(move_paddles).after(hold_ball),
+                    apply_ball_bounce.after(handle_collisions),
+                    record_replay.after(apply_ball_bounce),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not_paused)
+                    .run_if(not_replaying),
+            )
+            .add_systems(
+                FixedUpdate,
+                play_replay
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not_paused)
+                    .run_if(replaying),
+            )
+            .add_systems(
+                Update,
+                (
+                    tick_serve_countdown,
+                    slow_down_match_point.after(tick_serve_countdown),
+                    (
+                        handle_player_input,
+                        handle_mouse_input,
+                        handle_player_two_input,
+                        launch_held_balls,
+                    ),
+                    (move_ai_paddle, move_ai_gutter_paddle.after(move_ai_paddle)),
+                    detect_scoring,
+                    // Everything drawing from `RngResource` runs in a set order,
+                    // so a seeded run always draws the same numbers for the
+                    // same things
+                    recover_lost_balls
+                        .after(detect_scoring)
+                        .after(move_ai_gutter_paddle),
+                    // `detect_scoring` marks the ball that scored `OutOfPlay`
+                    // through `Commands`, which `reset_ball` only sees once
+                    // they've been applied
+                    apply_deferred
+                        .after(detect_scoring)
+                        .after(recover_lost_balls)
+                        .before(reset_ball),
+                    reset_ball
+                        .after(detect_scoring)
+                        .after(project_positions)
+                        .after(recover_lost_balls),
+                    update_score.after(detect_scoring),
+                    play_score_sound.after(detect_scoring),
+                    check_win.after(update_score),
+                    play_collision_sound,
+                    count_rally.after(detect_scoring),
+                    time_match,
+                    (
+                        lose_lives.after(update_score),
+                        speed_up_survival_ball,
+                        show_life_icons.after(lose_lives),
+                        run_match_timer.after(update_score),
+                        show_match_timer.after(run_match_timer),
+                        show_streak.after(update_score).run_if(on_event::<ScoredEvent>()),
+                    ),
+                    spawn_extra_ball_on_key.after(spawn_power_ups),
+                    serve_balls
+                        .after(tick_serve_countdown)
+                        .after(launch_held_balls)
+                        .after(spawn_extra_ball_on_key)
+                        .run_if(serve_countdown_finished),
+                    (
+                        spawn_power_ups.after(reset_ball),
+                        collect_power_ups,
+                        expire_power_ups.after(collect_power_ups),
+                    ),
+                    (
+                        spawn_trail,
+                        fade_trail,
+                        burst_on_paddle_hits,
+                        update_particles,
+                        color_balls_by_speed.after(expire_power_ups),
+                        trigger_screen_shake,
+                        rumble_on_player_hits,
+                        point_serve_arrow.after(tick_serve_countdown).after(reset_ball),
+                        zoom_on_match_point.after(tick_serve_countdown),
+                    ),
+                    start_replay,
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not_paused)
+                    .run_if(not_replaying),
+            )
+            .add_systems(
+                Update,
+                (toggle_pause, pause_on_focus_lost, show_ball_path)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_scoreboard.after(update_score).run_if(on_event::<ScoredEvent>()),
+                    project_positions,
+                    follow_ball_shadows.after(project_positions),
+                    (resize_court.before(project_positions), reposition_center_line)
+                        .run_if(resource_changed::<CourtDimensions>()),
+                    // Every frame rather than when the court changes, since the
+                    // window can change shape around a court that stays put
+                    place_letterbox_bars,
+                    apply_gutter_height.run_if(resource_changed::<GameConfig>()),
+                    shake_camera,
+                    apply_theme.run_if(resource_changed::<Theme>()),
+                ),
+            );
+    }
+}
+
+fn apply_target_fps(config: Res<GameConfig>, mut fixed_time: ResMut<Time<Fixed>>) {
+    fixed_time.set_timestep_hz(config.target_fps);
+}
+
+fn apply_vsync(settings: Res<WindowSettings>, mut windows: Query<&mut Window>) {
+    for mut window in &mut windows {
+        window.present_mode = present_mode(settings.vsync);
+    }
+}
+
+fn serve_countdown_finished(countdown: Res<ServeCountdown>) -> bool {
+    countdown.0.finished()
+}
+
+// The one place balls get their velocity from a serve, whether it's the start
+// of the match, after a point or an extra ball mid-rally
+fn serve_balls(
+    mut commands: Commands,
+    mut balls: Query<(Entity, &mut Velocity, &AwaitingServe), With<Ball>>,
+) {
+    for (entity, mut velocity, serve) in &mut balls {
+        velocity.0 = serve.0;
+        commands.entity(entity).remove::<AwaitingServe>();
+    }
+}
+
+fn start_serve_countdown(mut countdown: ResMut<ServeCountdown>) {
+    countdown.0.reset();
+}
+
+fn reset_start_delay(mut delay: ResMut<StartDelay>, config: Res<GameConfig>) {
+    delay.0 = Timer::from_seconds(config.start_delay_secs, TimerMode::Once);
+}
+
+fn tick_serve_countdown(
+    time: Res<Time>,
+    mut delay: ResMut<StartDelay>,
+    mut countdown: ResMut<ServeCountdown>,
+    mut text: Query<(&mut Text, &mut Visibility), With<CountdownText>>,
+) {
+    // Nothing counts down until the start of the match is out of the way
+    delay.0.tick(time.delta());
+    if !delay.0.finished() {
+        if let Ok((_, mut visibility)) = text.get_single_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    }
+
+    countdown.0.tick(time.delta());
+
+    if let Ok((mut text, mut visibility)) = text.get_single_mut() {
+        if countdown.0.finished() {
+            *visibility = Visibility::Hidden;
+        } else {
+            // Show 3, 2, 1 rather than 2, 1, 0
+            let remaining = countdown.0.remaining_secs().ceil();
+            text.sections[0].value = remaining.to_string();
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+// Either side being one point from winning
+fn is_match_point(score: &Score, config: &GameConfig) -> bool {
+    config.has_won(score.left + 1, score.right) || config.has_won(score.right + 1, score.left)
+}
+
+// Slows the game down once the ball is served at match point. Scoring starts
+// the serve countdown again, which brings it back to full speed.
+fn slow_down_match_point(
+    score: Res<Score>,
+    countdown: Res<ServeCountdown>,
+    config: Res<GameConfig>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    let scale = if countdown.0.finished() && is_match_point(&score, &config) {
+        config.match_point_time_scale
+    } else {
+        1.
+    };
+
+    // Only write on a change so `is_changed` means something to readers
+    if time_scale.0 != scale {
+        time_scale.0 = scale;
+    }
+}
+
+fn reset_time_scale(mut time_scale: ResMut<TimeScale>) {
+    *time_scale = TimeScale::default();
+}
+
+// How far from the ball's center the serve arrow sits, and how big it is
+const SERVE_ARROW_DISTANCE: f32 = 25.;
+const SERVE_ARROW_SIZE: f32 = 8.;
+
+// Shows which way the ball is going to go while it waits to be served. The
+// serve's velocity is already on the ball by then, so the arrow just reads
+// it. Once the ball is off the arrow goes.
+fn point_serve_arrow(
+    mut commands: Commands,
+    countdown: Res<ServeCountdown>,
+    balls: Query<(&Position, &Velocity, Option<&AwaitingServe>), (With<Ball>, Without<OutOfPlay>)>,
+    mut arrows: Query<(Entity, &mut Transform), With<ServeArrow>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let waiting = balls.iter().next().filter(|_| !countdown.0.finished());
+    let Some((position, velocity, serve)) = waiting else {
+        for (entity, _) in &arrows {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
+
+    let direction = ball_heading(velocity, serve).normalize_or_zero();
+    // The triangle points straight up before it's turned
+    let angle = direction.y.atan2(direction.x) - std::f32::consts::FRAC_PI_2;
+    let transform = Transform::from_translation(
+        (position.0 + direction * SERVE_ARROW_DISTANCE).extend(SERVE_ARROW_Z),
+    )
+    .with_rotation(Quat::from_rotation_z(angle));
+
+    if let Ok((_, mut arrow)) = arrows.get_single_mut() {
+        *arrow = transform;
+    } else {
+        let mesh = Mesh::from(shape::RegularPolygon::new(SERVE_ARROW_SIZE, 3));
+        commands.spawn((
+            ServeArrow,
+            Gameplay,
+            MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: materials.add(ColorMaterial::from(Color::WHITE)),
+                transform,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn not_paused(paused: Res<Paused>) -> bool {
+    !paused.0
+}
+
+fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<InputBindings>,
+    mut paused: ResMut<Paused>,
+) {
+    // `just_pressed` so holding the key down doesn't flicker between states
+    if keyboard_input.just_pressed(bindings.pause) {
+        paused.0 = !paused.0;
+    }
+}
+
+// Pauses the match when the game's window loses focus, so the AI can't run up
+// the score while you're away. Menus don't need pausing, so this only runs
+// during play.
+fn pause_on_focus_lost(
+    mut events: EventReader<WindowFocused>,
+    mut paused: ResMut<Paused>,
+    config: Res<GameConfig>,
+    // Whether the current pause is one we started, as opposed to the player
+    mut paused_by_us: Local<bool>,
+) {
+    for event in events.read() {
+        if !event.focused {
+            if !paused.0 {
+                paused.0 = true;
+                *paused_by_us = true;
+            }
+        } else if config.resume_on_focus && *paused_by_us && paused.0 {
+            paused.0 = false;
+        }
+    }
+
+    // If the player unpaused by hand, a later refocus shouldn't count
+    if !paused.0 {
+        *paused_by_us = false;
+    }
+}
+
+fn unpause(mut paused: ResMut<Paused>) {
+    paused.0 = false;
+}
+
+// How many replay frames play each step, and the most steps we keep (half a
+// minute's worth) so a very long rally can't use up memory without limit
+const REPLAY_SPEED: f32 = 0.4;
+const MAX_REPLAY_FRAMES: usize = 60 * 30;
+
+fn replaying(replay: Res<ReplayMode>) -> bool {
+    replay.0.is_some()
+}
+
+fn not_replaying(replay: Res<ReplayMode>) -> bool {
+    replay.0.is_none()
+}
+
+fn reset_replay(mut buffer: ResMut<ReplayBuffer>, mut replay: ResMut<ReplayMode>) {
+    *buffer = ReplayBuffer::default();
+    replay.0 = None;
+}
+
+fn record_replay(
+    mut buffer: ResMut<ReplayBuffer>,
+    countdown: Res<ServeCountdown>,
+    things: Query<(Entity, &Position), Or<(With<Ball>, With<Paddle>)>>,
+) {
+    // Keep hold of the last point while waiting for the next serve, so it
+    // can be replayed in the meantime
+    if !countdown.0.finished() {
+        buffer.recording = false;
+        return;
+    }
+
+    if !buffer.recording {
+        buffer.frames.clear();
+        buffer.recording = true;
+    }
+
+    if buffer.frames.len() == MAX_REPLAY_FRAMES {
+        buffer.frames.pop_front();
+    }
+    buffer.frames.push_back(things.iter().map(|(entity, position)| (entity, position.0)).collect());
+}
+
+// R replays the last point, but only between points
+fn start_replay(
+    keyboard_input: Res<Input<KeyCode>>,
+    buffer: Res<ReplayBuffer>,
+    countdown: Res<ServeCountdown>,
+    mut replay: ResMut<ReplayMode>,
+    things: Query<(Entity, &Position), Or<(With<Ball>, With<Paddle>)>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::R)
+        || countdown.0.finished()
+        || buffer.frames.is_empty()
+    {
+        return;
+    }
+
+    replay.0 = Some(Replay {
+        frame: 0.,
+        resume: things.iter().map(|(entity, position)| (entity, position.0)).collect(),
+    });
+}
+
+// Drives the balls and paddles from the replay buffer in place of the usual
+// physics systems
+fn play_replay(
+    mut replay: ResMut<ReplayMode>,
+    buffer: Res<ReplayBuffer>,
+    mut things: Query<(&mut Position, &mut PreviousPosition)>,
+) {
+    let Some(playback) = &mut replay.0 else {
+        return;
+    };
+
+    let last_frame = buffer.frames.len().saturating_sub(1) as f32;
+    let (positions, finished) = if playback.frame > last_frame {
+        (std::mem::take(&mut playback.resume), true)
+    } else {
+        let positions = sample_replay(&buffer.frames, playback.frame);
+        playback.frame += REPLAY_SPEED;
+        (positions, false)
+    };
+
+    for (entity, replayed) in positions {
+        // Balls from a multi-ball point may have been despawned since
+        if let Ok((mut position, mut previous_position)) = things.get_mut(entity) {
+            // Jump straight back at the end rather than sliding there
+            previous_position.0 = if finished { replayed } else { position.0 };
+            position.0 = replayed;
+        }
+    }
+
+    if finished {
+        replay.0 = None;
+    }
+}
+
+// Where everything was `frame` steps into the replay, blending between the
+// two nearest recorded steps
+fn sample_replay(frames: &VecDeque<ReplayFrame>, frame: f32) -> ReplayFrame {
+    let index = frame.floor() as usize;
+    let Some(before) = frames.get(index) else {
+        return Vec::new();
+    };
+    let Some(after) = frames.get(index + 1) else {
+        return before.clone();
+    };
+
+    let blend = frame.fract();
+    before
+        .iter()
+        .map(|&(entity, position)| {
+            let next = after.iter().find(|(other, _)| *other == entity);
+            let position = next.map_or(position, |&(_, next)| position.lerp(next, blend));
+            (entity, position)
+        })
+        .collect()
+}
+
+fn detect_scoring(
+    mut commands: Commands,
+    balls: Query<(Entity, &Position, &PreviousPosition, &Shape), (With<Ball>, Without<OutOfPlay>)>,
+    score_walls: Query<(&Position, &Shape, &Side), (With<ScoreWall>, Without<Ball>)>,
+    mut events: EventWriter<ScoredEvent>,
+) {
+    let mut in_play = 0;
+    let mut exited = Vec::new();
+    for (entity, position, previous_position, shape) in &balls {
+        // Sweeping from where the ball was catches one moving fast enough to
+        // jump over a score wall, and the overlap check one that started
+        // the step already touching it
+        let reached = score_walls.iter().find(|(wall_position, wall_shape, _)| {
+            swept_collision(
+                previous_position.0,
+                position.0,
+                shape.0,
+                wall_position.0,
+                wall_shape.0,
+            )
+            .is_some()
+                || collide(
+                    position.0.extend(0.),
+                    shape.0,
+                    wall_position.0.extend(0.),
+                    wall_shape.0,
+                )
+                .is_some()
+        });
+        match reached {
+            // Going off one end is a point for the other side
+            Some((_, _, side)) => exited.push((entity, side.opposite())),
+            None => in_play += 1,
+        }
+    }
+
+    // Every ball that leaves the court scores a point. While other balls
+    // are still in play it's simply removed; the last one is kept back
+    // for `reset_ball` to serve again. Marking it `OutOfPlay` means it
+    // can only score once, even if it stays off-screen for a while.
+    let last = exited.len().checked_sub(1);
+    for (index, (entity, side)) in exited.into_iter().enumerate() {
+        // Here we write the events using our EventWriter
+        events.send(ScoredEvent { side });
+
+        if in_play == 0 && Some(index) == last {
+            commands.entity(entity).insert(OutOfPlay);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// A ball that gets this far out has gone wrong somehow rather than scored:
+// scoring happens as soon as it touches a score wall
+fn is_lost(position: Vec2, court_size: Vec2) -> bool {
+    !position.is_finite() || position.x.abs() > court_size.x || position.y.abs() > court_size.y
+}
+
+// Safety net for physics bugs. Rather than leaving a runaway ball flying off
+// forever, put it back in the middle and serve it again.
+fn recover_lost_balls(
+    mut commands: Commands,
+    mut balls: Query<
+        (
+            Entity,
+            &mut Position,
+            &mut PreviousPosition,
+            &mut Velocity,
+            &mut RallySpeed,
+            &mut Spin,
+        ),
+        (With<Ball>, Without<OutOfPlay>),
+    >,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<RngResource>,
+) {
+    for (entity, mut position, mut previous_position, mut velocity, mut speed, mut spin) in
+        &mut balls
+    {
+        if !is_lost(position.0, court.size()) {
+            continue;
+        }
+
+        warn!("Re-serving a ball that got lost at {:?}", position.0);
+        let direction = if rng.0.gen() { 1. } else { -1. };
+        position.0 = Vec2::ZERO;
+        previous_position.0 = Vec2::ZERO;
+        velocity.0 = Vec2::ZERO;
+        speed.0 = config.ball_speed;
+        spin.0 = 0.;
+
+        // No countdown for this, so it goes straight back into play
+        let serve = serve_ball(direction, config.serve_spread, &mut rng.0);
+        commands.entity(entity).insert(AwaitingServe(serve.0));
+    }
+}
+
+fn update_score(
+    mut score: ResMut<Score>,
+    mut stats: ResMut<MatchStats>,
+    mut events: EventReader<ScoredEvent>,
+) {
+    for event in events.read() {
+        let streak = stats.count_point(event.side);
+        let points = if streak.is_multiple_of(STREAK_FOR_BONUS) { 2 } else { 1 };
+        match event.side {
+            Side::Left => score.left += points,
+            Side::Right => score.right += points,
+        }
+
+        println!("Score: {} - {}", score.left, score.right);
+    }
+}
+
+// Runs after `update_score`, so the score already includes these points
+fn check_win(
+    mut commands: Commands,
+    mut events: EventReader<ScoredEvent>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    config: Res<GameConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Only the clock ends a timed match
+    if *mode == GameMode::Timed {
+        events.clear();
+        return;
+    }
+
+    for event in events.read() {
+        if config.has_won(score.of(event.side), score.of(event.side.opposite())) {
+            commands.insert_resource(Winner::from(event.side));
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+fn count_rally(
+    mut collisions: EventReader<BallCollisionEvent>,
+    mut scored: EventReader<ScoredEvent>,
+    paddles: Query<&Shape, With<Paddle>>,
+    mut stats: ResMut<MatchStats>,
+) {
+    for event in collisions.read() {
+        if Bounce::new(event.collision, paddles.get(event.entity).ok()) == Bounce::Paddle {
+            stats.current_rally += 1;
+            stats.paddle_hits += 1;
+        }
+    }
+
+    if scored.read().count() > 0 {
+        stats.end_rally();
+    }
+}
+
+// Pausing stops the clock, since this only runs during play
+fn time_match(
+    mut stats: ResMut<MatchStats>,
+    balls: Query<(&RallySpeed, Option<&ActivePowerUp>), (With<Ball>, Without<OutOfPlay>)>,
+    time: Res<Time>,
+) {
+    stats.match_secs += time.delta_seconds();
+    for (speed, power_up) in &balls {
+        let speed = speed.0 * power_up.map_or(1., |power_up| power_up.kind.speed_scale());
+        stats.top_speed = stats.top_speed.max(speed);
+    }
+}
+
+fn reset_lives(mut lives: ResMut<Lives>) {
+    *lives = Lives::default();
+}
+
+// In survival mode every point the wall wins is a life lost, and losing the
+// last one ends the game
+fn lose_lives(
+    mut commands: Commands,
+    mut events: EventReader<ScoredEvent>,
+    mode: Res<GameMode>,
+    player_side: Res<PlayerSide>,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if *mode != GameMode::Survival {
+        events.clear();
+        return;
+    }
+
+    // Only the wall's points are misses
+    let wall_side = player_side.0.opposite();
+    for event in events.read() {
+        if event.side != wall_side || lives.0 == 0 {
+            continue;
+        }
+
+        lives.0 -= 1;
+        if lives.0 == 0 {
+            commands.insert_resource(Winner::from(wall_side));
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn reset_match_timer(mut timer: ResMut<MatchTimer>) {
+    *timer = MatchTimer::default();
+}
+
+// Ends a timed match once the time is up. This only runs while the match is
+// being played, so the clock stops whenever the game is paused.
+fn run_match_timer(
+    mut commands: Commands,
+    mut timer: ResMut<MatchTimer>,
+    time: Res<Time>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if *mode != GameMode::Timed || !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let winner = match score.left.cmp(&score.right) {
+        std::cmp::Ordering::Greater => Winner::Left,
+        std::cmp::Ordering::Less => Winner::Right,
+        std::cmp::Ordering::Equal => Winner::Draw,
+    };
+    commands.insert_resource(winner);
+    next_state.set(GameState::GameOver);
+}
+
+fn show_match_timer(timer: Res<MatchTimer>, mut text: Query<&mut Text, With<MatchTimerText>>) {
+    // Round up, so the clock only reads 0:00 once time is actually up
+    let secs = timer.0.remaining_secs().ceil() as u32;
+    for mut text in &mut text {
+        text.sections[0].value = format!("{}:{:02}", secs / 60, secs % 60);
+    }
+}
+
+fn show_streak(stats: Res<MatchStats>, mut text: Query<(&mut Text, &StreakText)>) {
+    for (mut text, streak_text) in &mut text {
+        let streak = stats.streak_of(streak_text.0);
+        text.sections[0].value = if streak > 1 {
+            format!("{} IN A ROW", streak)
+        } else {
+            String::new()
+        };
+    }
+}
+
+fn speed_up_survival_ball(
+    mut balls: Query<&mut RallySpeed, (With<Ball>, Without<OutOfPlay>)>,
+    time: Res<Time>,
+    countdown: Res<ServeCountdown>,
+    mode: Res<GameMode>,
+    config: Res<GameConfig>,
+) {
+    if *mode != GameMode::Survival || !countdown.0.finished() {
+        return;
+    }
+
+    for mut speed in &mut balls {
+        let faster = speed.0 + config.survival_speed_up * time.delta_seconds();
+        speed.0 = faster.min(config.max_ball_speed);
+    }
+}
+
+fn reset_stats(mut stats: ResMut<MatchStats>) {
+    *stats = MatchStats::default();
+}
+
+// Runs whenever a point is scored, and when the score is reset for a new match
+fn update_scoreboard(
+    mut left_score: Query<&mut Text, With<LeftScoreText>>,
+    mut right_score: Query<&mut Text, (With<RightScoreText>, Without<LeftScoreText>)>,
+    score: Res<Score>,
+) {
+    if let Ok(mut left_score) = left_score.get_single_mut() {
+        left_score.sections[0].value = score.left.to_string();
+    }
+
+    if let Ok(mut right_score) = right_score.get_single_mut() {
+        right_score.sections[0].value = score.right.to_string();
+    }
+}
+
+// Sweeps a box of `moving_size` from `start` to `end` against the box at
+// `position`. Returns the fraction of the movement completed at the moment of
+// impact, and which side of the obstacle was hit. Boxes that are already
+// overlapping at `start` are not reported.
+fn swept_collision(
+    start: Vec2,
+    end: Vec2,
+    moving_size: Vec2,
+    position: Vec2,
+    size: Vec2,
+) -> Option<(f32, Collision)> {
+    // Grow the obstacle by half the moving box in every direction, so the
+    // moving box can be treated as a single point travelling along a segment
+    let half_extents = (moving_size + size) / 2.;
+    let min = position - half_extents;
+    let max = position + half_extents;
+    let delta = end - start;
+
+    // The range of time over which the segment is between each pair of faces
+    let slab = |start: f32, delta: f32, min: f32, max: f32| {
+        if delta == 0. {
+            if start > min && start < max {
+                Some((f32::NEG_INFINITY, f32::INFINITY))
+            } else {
+                None
+            }
+        } else {
+            let t1 = (min - start) / delta;
+            let t2 = (max - start) / delta;
+            Some((t1.min(t2), t1.max(t2)))
+        }
+    };
+
+    let (x_enter, x_exit) = slab(start.x, delta.x, min.x, max.x)?;
+    let (y_enter, y_exit) = slab(start.y, delta.y, min.y, max.y)?;
+    let enter = x_enter.max(y_enter);
+    let exit = x_exit.min(y_exit);
+
+    if enter > exit || !(0. ..=1.).contains(&enter) {
+        return None;
+    }
+
+    // Whichever axis was entered last is the face we went through
+    let collision = if x_enter > y_enter {
+        if delta.x > 0. {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if delta.y > 0. {
+        Collision::Bottom
+    } else {
+        Collision::Top
+    };
+
+    Some((enter, collision))
+}
+
+fn handle_collisions(
+    mut balls: Query<
+        (Entity, &Velocity, &mut Position, &PreviousPosition, &Shape, &CollisionLayer),
+        With<Ball>,
+    >,
+    // We can collide with anything else that has a shape, a position and a
+    // collision layer
+    other_things: Query<(Entity, &Position, &Shape, &CollisionLayer, Has<Gutter>), Without<Ball>>,
+    config: Res<GameConfig>,
+    mode: Res<GameMode>,
+    mut events: EventWriter<BallCollisionEvent>,
+) {
+    // Balls can be in each other's way too, so note where they all are before
+    // any of them get moved
+    let balls_in_the_way: Vec<_> = balls
+        .iter()
+        .map(|(entity, _, position, _, shape, layer)| (entity, position.0, shape.0, *layer))
+        .collect();
+    let obstacles: Vec<_> = other_things
+        .iter()
+        // With paddles guarding them, the gutters are for scoring through
+        .filter(|(.., is_gutter)| !(*is_gutter && mode.has_gutter_paddles()))
+        .map(|(entity, position, shape, layer, _)| (entity, position.0, shape.0, *layer))
+        .chain(balls_in_the_way)
+        .collect();
+
+    for (ball, velocity, mut ball_position, previous_position, ball_shape, ball_layer) in
+        &mut balls
+    {
+        let hittable: Vec<_> = obstacles
+            .iter()
+            .filter(|(entity, _, _, layer)| {
+                *entity != ball && ball_layer.collides_with(*layer, &config)
+            })
+            .collect();
+
+        // Only report collisions the ball is moving into. Once it's been
+        // turned around an overlap can last another frame or two, and that
+        // shouldn't count as hitting the same thing again.
+        let mut send = |entity, collision| {
+            if heading_into(velocity.0, collision) {
+                events.send(BallCollisionEvent {
+                    ball,
+                    entity,
+                    collision,
+                });
+            }
+        };
+
+        // A fast ball can move further than its own width in a single step and
+        // skip straight over a paddle, so test the whole path it took this
+        // frame and stop it at whatever it reached first
+        let first_hit = hittable
+            .iter()
+            .filter_map(|(entity, position, shape, _)| {
+                swept_collision(
+                    previous_position.0,
+                    ball_position.0,
+                    ball_shape.0,
+                    *position,
+                    *shape,
+                )
+                .map(|(time_of_impact, collision)| (time_of_impact, collision, *entity))
+            })
+            .min_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+        if let Some((time_of_impact, collision, entity)) = first_hit {
+            ball_position.0 = previous_position.0.lerp(ball_position.0, time_of_impact);
+            send(entity, collision);
+            continue;
+        }
+
+        // The ball didn't run into anything, but something may have run into
+        // the ball (e.g. a paddle moving over it)
+        for (entity, position, shape, _) in &hittable {
+            if let Some((resolved, collision)) =
+                overlap_collision(ball_position.0, ball_shape.0, *position, *shape)
+            {
+                ball_position.0 = resolved;
+                send(*entity, collision);
+            }
+        }
+    }
+}
+
+// Checks whether the ball is overlapping an obstacle right now. If it is,
+// returns where the ball should be and which side of the obstacle it hit.
+fn overlap_collision(
+    ball_position: Vec2,
+    ball_size: Vec2,
+    position: Vec2,
+    size: Vec2,
+) -> Option<(Vec2, Collision)> {
+    let collision = collide(ball_position.extend(0.), ball_size, position.extend(0.), size)?;
+
+    if collision == Collision::Inside {
+        // The ball's center has ended up inside the obstacle, so there's no
+        // side to bounce off yet. Push it back out the closest way and treat
+        // that as the side it hit.
+        Some(eject_ball(ball_position, ball_size, position, size))
+    } else {
+        Some((ball_position, collision))
+    }
+}
+
+// Moves a ball that is overlapping an obstacle to just outside its nearest
+// edge, along whichever axis it has sunk into the least. Returns the new
+// position and the side of the obstacle it came out of.
+fn eject_ball(
+    ball_position: Vec2,
+    ball_size: Vec2,
+    position: Vec2,
+    size: Vec2,
+) -> (Vec2, Collision) {
+    let half_extents = (ball_size + size) / 2.;
+    let offset = ball_position - position;
+    let penetration = half_extents - offset.abs();
+
+    let mut ejected = ball_position;
+    let collision = if penetration.x < penetration.y {
+        ejected.x = position.x + half_extents.x * offset.x.signum();
+        if offset.x < 0. {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else {
+        ejected.y = position.y + half_extents.y * offset.y.signum();
+        if offset.y < 0. {
+            Collision::Bottom
+        } else {
+            Collision::Top
+        }
+    };
+
+    (ejected, collision)
+}
+
+fn heading_into(velocity: Vec2, collision: Collision) -> bool {
+    match collision {
+        Collision::Left => velocity.x > 0.,
+        Collision::Right => velocity.x < 0.,
+        Collision::Top => velocity.y < 0.,
+        Collision::Bottom => velocity.y > 0.,
+        Collision::Inside => false,
+    }
+}
+
+fn apply_ball_bounce(
+    mut events: EventReader<BallCollisionEvent>,
+    mut balls: Query<(&mut Velocity, &mut RallySpeed, &mut Spin, &Position, &Shape), With<Ball>>,
+    other_things: Query<(&Position, &Shape, Option<&Velocity>, Has<Paddle>), Without<Ball>>,
+    config: Res<GameConfig>,
+) {
+    for event in events.read() {
+        // Another ball is bounced off like a wall. Copy it out first, since
+        // the ball doing the bouncing borrows the same query.
+        let other_ball = balls
+            .get(event.entity)
+            .ok()
+            .map(|(_, _, _, position, shape)| (Position(position.0), Shape(shape.0)));
+        let Ok((mut velocity, mut rally_speed, mut spin, ball_position, _)) =
+            balls.get_mut(event.ball)
+        else {
+            continue;
+        };
+
+        let bounce = if let Some((position, shape)) = &other_ball {
+            let thing = (position, shape, None, false);
+            bounce_ball(&mut velocity, ball_position.0, event.collision, thing, &config)
+        } else if let Ok(thing) = other_things.get(event.entity) {
+            bounce_ball(&mut velocity, ball_position.0, event.collision, thing, &config)
+        } else {
+            continue;
+        };
+        if bounce == Bounce::Paddle {
+            let sped_up = (rally_speed.0 + config.rally_speed_up) * config.restitution;
+            rally_speed.0 = sped_up.min(config.max_ball_speed);
+
+            // The curve pulls back against the way the paddle was moving, so
+            // a ball swung steeply up bends back down later on
+            let paddle_velocity = other_things.get(event.entity).ok().and_then(|thing| thing.2);
+            if let (true, Some(paddle_velocity)) = (config.curve_ball, paddle_velocity) {
+                spin.0 = -paddle_velocity.0.y * velocity.0.x.signum() * SPIN_PER_PADDLE_SPEED;
+            }
+        }
+    }
+}
+
+fn play_collision_sound(
+    mut commands: Commands,
+    mut events: EventReader<BallCollisionEvent>,
+    paddles: Query<&Shape, With<Paddle>>,
+    asset_server: Res<AssetServer>,
+    sounds: Res<Sounds>,
+    volume: Res<Volume>,
+) {
+    for event in events.read() {
+        let sound = match Bounce::new(event.collision, paddles.get(event.entity).ok()) {
+            Bounce::Paddle => &sounds.paddle_hit,
+            Bounce::Wall => &sounds.wall_hit,
+        };
+        play_sound(&mut commands, &asset_server, sound, *volume);
+    }
+}
+
+fn bounce_ball(
+    velocity: &mut Velocity,
+    ball_position: Vec2,
+    collision: Collision,
+    (position, shape, paddle_velocity, is_paddle): (&Position, &Shape, Option<&Velocity>, bool),
+    config: &GameConfig,
+) -> Bounce {
+    velocity.0 = reflect_velocity(velocity.0, collision);
+
+    // Hitting the face of a paddle lets the player aim their return. Gutters
+    // (and the ends of a paddle) are plain reflections.
+    let bounce = Bounce::new(collision, is_paddle.then_some(shape));
+    if bounce != Bounce::Paddle {
+        return bounce;
+    }
+
+    if shape.0.x > shape.0.y {
+        // A gutter paddle is an end paddle turned on its side, so aim the same
+        // way with x and y swapped over
+        let mut turned = Velocity(Vec2::new(velocity.0.y, velocity.0.x));
+        aim_off_paddle(&mut turned, ball_position.x, position.0.x, shape.0.x);
+        if let Some(paddle_velocity) = paddle_velocity {
+            add_paddle_spin(&mut turned, paddle_velocity.0.x, config.paddle_spin);
+        }
+        velocity.0 = Vec2::new(turned.0.y, turned.0.x);
+    } else {
+        aim_off_paddle(velocity, ball_position.y, position.0.y, shape.0.y);
+        if let Some(paddle_velocity) = paddle_velocity {
+            add_paddle_spin(velocity, paddle_velocity.0.y, config.paddle_spin);
+        }
+    }
+
+    bounce
+}
+
+// Sends the ball off at an angle depending on how far from the center of the
+// paddle it struck, keeping its speed the same
+fn aim_off_paddle(velocity: &mut Velocity, ball_y: f32, paddle_y: f32, paddle_height: f32) {
+    let offset = ((ball_y - paddle_y) / (paddle_height / 2.)).clamp(-1., 1.);
+    let angle = offset * MAX_BOUNCE_ANGLE;
+    let speed = velocity.0.length();
+
+    velocity.0 = Vec2::new(velocity.0.x.signum() * angle.cos(), angle.sin()) * speed;
+}
+
+// Carries some of a moving paddle's motion over into the ball, so swinging the
+// paddle into a hit sends it off steeper in that direction. The result is kept
+// within the same range of angles as aiming off the paddle, at the same speed.
+fn add_paddle_spin(velocity: &mut Velocity, paddle_velocity_y: f32, spin: f32) {
+    let speed = velocity.0.length();
+    let direction = velocity.0.normalize_or_zero();
+    let spun = Vec2::new(direction.x, direction.y + paddle_velocity_y * spin);
+
+    let angle = spun.y.atan2(spun.x.abs()).clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+    velocity.0 = Vec2::new(direction.x.signum() * angle.cos(), angle.sin()) * speed;
+}
+
+// The velocity a ball leaves the given side of an obstacle with. It always
+// ends up heading away from that side, so a ball already moving away from it
+// keeps going as it was.
+fn reflect_velocity(velocity: Vec2, collision: Collision) -> Vec2 {
+    match collision {
+        Collision::Left => Vec2::new(-velocity.x.abs(), velocity.y),
+        Collision::Right => Vec2::new(velocity.x.abs(), velocity.y),
+        Collision::Top => Vec2::new(velocity.x, velocity.y.abs()),
+        Collision::Bottom => Vec2::new(velocity.x, -velocity.y.abs()),
+        Collision::Inside => velocity,
+    }
+}
+
+// The last direction key the player held, and how much longer it counts
+// for after being let go
+#[derive(Default)]
+struct InputBuffer {
+    direction: f32,
+    secs_left: f32,
+}
+
+// What the player steers with, and which side of the court they're on
+#[derive(SystemParam)]
+struct PlayerControls<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    bindings: Res<'w, InputBindings>,
+    input_mode: Res<'w, InputMode>,
+    player_side: Res<'w, PlayerSide>,
+}
+
+fn handle_player_input(
+    controls: PlayerControls,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut paddles: Query<&mut TargetVelocity, (With<Paddle>, With<Player>)>,
+    mut gutter_paddles: Query<(&mut TargetVelocity, &Side), (With<GutterPaddle>, Without<Player>)>,
+    mut buffer: Local<InputBuffer>,
+    mut warned: Local<bool>,
+) {
+    let PlayerControls {
+        keyboard_input,
+        gamepads,
+        gamepad_buttons,
+        gamepad_axes,
+        bindings,
+        input_mode,
+        player_side,
+    } = controls;
+
+    // The AI takes the player's paddle too in a demo match
+    warn_on_unexpected_count("player paddles", paddles.iter().len(), 0..=1, &mut warned);
+
+    let held = if keyboard_input.pressed(bindings.up) {
+        Some(1.)
+    } else if keyboard_input.pressed(bindings.down) {
+        Some(-1.)
+    } else {
+        None
+    };
+    // The buffer only starts running down once the key's let go
+    if let Some(direction) = held {
+        buffer.direction = direction;
+        buffer.secs_left = config.input_buffer_secs;
+    } else {
+        buffer.secs_left -= time.delta_seconds();
+    }
+    let buffered = if buffer.secs_left > 0. {
+        buffer.direction
+    } else {
+        0.
+    };
+
+    let gamepad = gamepad_direction(&gamepads, &gamepad_buttons, &gamepad_axes);
+
+    for mut target in &mut paddles {
+        target.0.y = match *input_mode {
+            InputMode::Keyboard => held.unwrap_or(buffered),
+            InputMode::Gamepad => gamepad.y,
+            // `handle_mouse_input` steers the paddle instead
+            InputMode::Mouse => continue,
+        };
+    }
+
+    // In four paddle mode the player's gutter paddle slides across with the
+    // other two directions
+    for (mut target, side) in &mut gutter_paddles {
+        if *side != player_side.0 {
+            continue;
+        }
+        target.0.x = match *input_mode {
+            InputMode::Keyboard if keyboard_input.pressed(bindings.left) => -1.,
+            InputMode::Keyboard if keyboard_input.pressed(bindings.right) => 1.,
+            InputMode::Keyboard => 0.,
+            InputMode::Gamepad => gamepad.x,
+            InputMode::Mouse => continue,
+        };
+    }
+}
+
+// Which way the first connected gamepad is asking the paddles to go, across
+// in x and up and down in y. If it disconnects we just stop seeing it here,
+// and the paddles stop.
+fn gamepad_direction(
+    gamepads: &Gamepads,
+    gamepad_buttons: &Input<GamepadButton>,
+    gamepad_axes: &Axis<GamepadAxis>,
+) -> Vec2 {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return Vec2::ZERO;
+    };
+
+    let pressed = |button_type| gamepad_buttons.pressed(GamepadButton::new(gamepad, button_type));
+    let axis = |positive, negative, stick_type| {
+        if pressed(positive) {
+            1.
+        } else if pressed(negative) {
+            -1.
+        } else {
+            // Unlike the D-pad the stick is analog, so the paddle moves slower
+            // the less it's pushed
+            let stick = gamepad_axes.get(GamepadAxis::new(gamepad, stick_type)).unwrap_or(0.);
+            stick.clamp(-1., 1.)
+        }
+    };
+
+    Vec2::new(
+        axis(
+            GamepadButtonType::DPadRight,
+            GamepadButtonType::DPadLeft,
+            GamepadAxisType::LeftStickX,
+        ),
+        axis(
+            GamepadButtonType::DPadUp,
+            GamepadButtonType::DPadDown,
+            GamepadAxisType::LeftStickY,
+        ),
+    )
+}
+
+// The mouse cursor, as seen through the camera
+#[derive(SystemParam)]
+struct Cursor<'w, 's> {
+    window: Query<'w, 's, &'static Window>,
+    camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl Cursor<'_, '_> {
+    // Where the cursor is on the court, or None when it's outside the window
+    fn world_position(&self) -> Option<Vec2> {
+        let window = self.window.get_single().ok()?;
+        let (camera, camera_transform) = self.camera.get_single().ok()?;
+        camera.viewport_to_world_2d(camera_transform, window.cursor_position()?)
+    }
+}
+
+// Moves the player's paddle toward the height of the cursor, as far as the
+// gutters allow, and their gutter paddle in four paddle mode across to it.
+// With the cursor outside the window there's nothing to follow, so the
+// paddles stop where they are.
+fn handle_mouse_input(
+    mut paddles: Query<(&mut TargetVelocity, &Position, &Shape), (With<Paddle>, With<Player>)>,
+    mut gutter_paddles: Query<
+        (&mut TargetVelocity, &Position, &Shape, &Side),
+        (With<GutterPaddle>, Without<Player>),
+    >,
+    controls: PlayerControls,
+    cursor: Cursor,
+    fixed_time: Res<Time<Fixed>>,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+) {
+    if *controls.input_mode != InputMode::Mouse {
+        return;
+    }
+
+    let cursor = cursor.world_position();
+    // Like the AI, aim to arrive in one step so the paddle doesn't overshoot
+    let step = config.paddle_speed * fixed_time.timestep().as_secs_f32();
+
+    for (mut target, position, shape) in &mut paddles {
+        let Some(cursor) = cursor else {
+            target.0.y = 0.;
+            continue;
+        };
+
+        let target_y = clamp_paddle_y(cursor.y, court.height, shape.0.y, config.gutter_height);
+        target.0.y = ((target_y - position.0.y) / step).clamp(-1., 1.);
+    }
+
+    for (mut target, position, shape, side) in &mut gutter_paddles {
+        if *side != controls.player_side.0 {
+            continue;
+        }
+        let Some(cursor) = cursor else {
+            target.0.x = 0.;
+            continue;
+        };
+
+        let target_x = clamp_paddle_x(cursor.x, court.width, shape.0.x);
+        target.0.x = ((target_x - position.0.x) / step).clamp(-1., 1.);
+    }
+}
+
+fn handle_player_two_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut paddles: Query<&mut TargetVelocity, (With<Paddle>, With<PlayerTwo>)>,
+    mut warned: Local<bool>,
+) {
+    // There's no second player at all when playing the AI
+    warn_on_unexpected_count("player two paddles", paddles.iter().len(), 0..=1, &mut warned);
+
+    for mut target in &mut paddles {
+        if keyboard_input.pressed(KeyCode::W) {
+            target.0.y = 1.;
+        } else if keyboard_input.pressed(KeyCode::S) {
+            target.0.y = -1.;
+        } else {
+            target.0.y = 0.;
+        }
+    }
+}
+
+// Systems used to quietly do nothing when `get_single` found the wrong number
+// of entities. This makes that visible, logging once each time the count goes
+// out of range rather than every frame it stays wrong.
+fn warn_on_unexpected_count(
+    what: &str,
+    count: usize,
+    expected: RangeInclusive<usize>,
+    warned: &mut bool,
+) {
+    if expected.contains(&count) {
+        *warned = false;
+    } else if !*warned {
+        warn!("Expected {:?} {} but found {}", expected, what, count);
+        *warned = true;
+    }
+}
+
+fn move_ai_paddle(
+    mut ai: Query<
+        (&mut TargetVelocity, &mut AiAim, &Position, &Side, Option<&Difficulty>),
+        (With<Ai>, With<Paddle>),
+    >,
+    balls: Query<(&Position, &Velocity, Option<&AwaitingServe>), (With<Ball>, Without<Ai>)>,
+    court: Res<CourtDimensions>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<RngResource>,
+) {
+    // How far the ball's center can get from the middle of the court before
+    // it bounces off a gutter
+    let bounce_y = court.height / 2. - config.gutter_height - config.ball_size / 2.;
+
+    for (mut target_velocity, mut aim, position, side, paddle_difficulty) in &mut ai {
+        let difficulty = paddle_difficulty.copied().unwrap_or(*difficulty);
+
+        // Only look at the ball again once it's time to react. In between,
+        // keep heading for wherever was decided last time.
+        if aim.react(time.delta(), difficulty) {
+            // Chase whichever ball heading our way will reach us first. A
+            // ball is heading our way when its x velocity points to the side
+            // of the court the paddle is on.
+            let target = balls
+                .iter()
+                .map(|(ball_position, velocity, serve)| {
+                    (ball_position, ball_heading(velocity, serve))
+                })
+                .filter(|(_, ball_velocity)| ball_velocity.x * side.direction() > 0.)
+                .min_by(|(a, _), (b, _)| {
+                    let a_distance = (a.0.x - position.0.x).abs();
+                    let b_distance = (b.0.x - position.0.x).abs();
+                    a_distance.total_cmp(&b_distance)
+                });
+
+            aim.target_y = if let Some((ball_position, ball_velocity)) = target {
+                let ball_y = if difficulty.predicts_ball() {
+                    predict_ball_landing(ball_position.0, ball_velocity, position.0.x, bounce_y)
+                } else {
+                    ball_position.0.y
+                };
+                Some(aim.aim_at(ball_y, difficulty, &mut rng))
+            } else {
+                aim.lose_track();
+                None
+            };
+        }
+
+        target_velocity.0.y = match aim.target_y {
+            Some(target_y) => {
+                let max_speed = config.ai_speed * difficulty.speed_scale();
+                ai_chase_speed(target_y - position.0.y, difficulty.dead_zone(), max_speed)
+            }
+            None => 0.,
+        };
+    }
+}
+
+// Drives the AI's gutter paddle in four paddle mode, which chases balls
+// heading for its gutter across the court the same way `move_ai_paddle`
+// chases them up and down. The player's is steered with the rest of their
+// input.
+fn move_ai_gutter_paddle(
+    mut paddles: Query<(&mut TargetVelocity, &mut AiAim, &Position), With<GutterPaddle>>,
+    balls: Query<(&Position, &Velocity, Option<&AwaitingServe>), (With<Ball>, Without<OutOfPlay>)>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<RngResource>,
+) {
+    let difficulty = *difficulty;
+
+    for (mut target_velocity, mut aim, position) in &mut paddles {
+        if aim.react(time.delta(), difficulty) {
+            // The nearest ball headed toward the gutter, which is the side of
+            // the court the paddle is on
+            let target = balls
+                .iter()
+                .filter(|(_, velocity, serve)| {
+                    ball_heading(velocity, *serve).y * position.0.y.signum() > 0.
+                })
+                .map(|(ball_position, _, _)| ball_position)
+                .min_by(|a, b| {
+                    let a_distance = (a.0.y - position.0.y).abs();
+                    let b_distance = (b.0.y - position.0.y).abs();
+                    a_distance.total_cmp(&b_distance)
+                });
+
+            // `target_y` is the spot along the paddle's own track, which for
+            // a gutter paddle runs across
+            aim.target_y = if let Some(ball_position) = target {
+                Some(aim.aim_at(ball_position.0.x, difficulty, &mut rng))
+            } else {
+                aim.lose_track();
+                None
+            };
+        }
+
+        target_velocity.0.x = match aim.target_y {
+            Some(target_x) => {
+                let max_speed = config.ai_speed * difficulty.speed_scale();
+                ai_chase_speed(target_x - position.0.x, difficulty.dead_zone(), max_speed)
+            }
+            None => 0.,
+        };
+    }
+}
+
+// How fast the AI paddle heads for a target `offset` pixels away, in paddle
+// speeds. It doesn't bother inside the dead zone, goes flat out from
+// `AI_EASE_DISTANCE` away, and slows down steadily in between so it settles
+// on the spot rather than overshooting it.
+fn ai_chase_speed(offset: f32, dead_zone: f32, max_speed: f32) -> f32 {
+    if offset.abs() <= dead_zone {
+        return 0.;
+    }
+    (offset / AI_EASE_DISTANCE * max_speed).clamp(-max_speed, max_speed)
+}
+
+// Works out the height the ball will be at when it reaches `target_x`, if it
+// carries on along `velocity` bouncing between `-bounce_y` and `bounce_y`
+fn predict_ball_landing(position: Vec2, velocity: Vec2, target_x: f32, bounce_y: f32) -> f32 {
+    if velocity.x == 0. || bounce_y <= 0. {
+        return position.y;
+    }
+
+    let travel = (target_x - position.x) / velocity.x;
+    let unbounced_y = position.y + velocity.y * travel;
+
+    // Bouncing back and forth is the same as carrying straight on through a
+    // series of mirrored courts, so fold the straight line back into ours
+    let height = 2. * bounce_y;
+    let folded = (unbounced_y + bounce_y).rem_euclid(2. * height);
+    if folded > height {
+        2. * height - folded - bounce_y
+    } else {
+        folded - bounce_y
+    }
+}
+
+fn move_ball(
+    mut balls: Query<
+        (
+            &mut Position,
+            &mut PreviousPosition,
+            &mut Velocity,
+            &mut Spin,
+            &RallySpeed,
+            Option<&ActivePowerUp>,
+        ),
+        With<Ball>,
+    >,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut warned: Local<bool>,
+) {
+    // Scoring always leaves one ball behind to serve, so running out of balls
+    // mid-match means something despawned one it shouldn't have
+    warn_on_unexpected_count("balls", balls.iter().len(), 1..=usize::MAX, &mut warned);
+
+    // This runs on the fixed timestep, so `time` is the length of a step
+    let distance_scale = time.delta_seconds() * time_scale.0;
+    for (mut position, mut previous_position, mut velocity, mut spin, speed, power_up) in
+        &mut balls
+    {
+        curve_ball(&mut velocity, &mut spin, distance_scale);
+        let power_up_scale = power_up.map_or(1., |power_up| power_up.kind.speed_scale());
+        let distance = speed.0 * power_up_scale * distance_scale;
+        step_ball(&mut position, &mut previous_position, &velocity, distance);
+    }
+}
+
+// Turns a ball's heading by its spin over `secs`, then lets some of the spin
+// wear off. It never curves round steeper than a paddle could send it.
+fn curve_ball(velocity: &mut Velocity, spin: &mut Spin, secs: f32) {
+    if spin.0 == 0. {
+        return;
+    }
+
+    let turned = Vec2::from_angle(spin.0 * secs).rotate(velocity.0);
+    let angle = turned.y.atan2(turned.x.abs()).clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+    let speed = velocity.0.length();
+    velocity.0 = Vec2::new(velocity.0.x.signum() * angle.cos(), angle.sin()) * speed;
+    spin.0 *= (-SPIN_DECAY * secs).exp();
+}
+
+// In assist mode, bends the path of any ball heading for the player's paddle
+// a little toward it. The further off the ball is the harder it's pulled,
+// but never past the steepest angle a paddle can send it at.
+fn assist_ball(
+    mut balls: Query<(&mut Velocity, &Position), With<Ball>>,
+    players: Query<&Position, (With<Paddle>, With<Player>)>,
+    assist: Res<AssistMode>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+) {
+    if !assist.0 {
+        return;
+    }
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+
+    let max_slope = MAX_BOUNCE_ANGLE.tan();
+    let pull = ASSIST_STRENGTH * time.delta_seconds() * time_scale.0;
+    for (mut velocity, position) in &mut balls {
+        let heading_for_player = (player.0.x - position.0.x) * velocity.0.x > 0.;
+        if !heading_for_player {
+            continue;
+        }
+
+        let slope = velocity.0.y / velocity.0.x.abs();
+        let slope = (slope + (player.0.y - position.0.y) * pull).clamp(-max_slope, max_slope);
+        let length = velocity.0.length();
+        velocity.0 = Vec2::new(velocity.0.x.signum(), slope).normalize() * length;
+    }
+}
+
+// Moves a ball `distance` pixels along its heading, as one fixed update step
+fn step_ball(
+    position: &mut Position,
+    previous_position: &mut PreviousPosition,
+    velocity: &Velocity,
+    distance: f32,
+) {
+    previous_position.0 = position.0;
+    // Only the direction of the ball's velocity matters. Serves and bounces
+    // can leave it longer or shorter than one, which would otherwise make
+    // angled shots faster than flat ones.
+    position.0 += velocity.0.normalize_or_zero() * distance;
+}
+
+fn reset_ball(
+    mut commands: Commands,
+    mut balls: Query<
+        (
+            Entity,
+            &mut Position,
+            &mut PreviousPosition,
+            &mut Velocity,
+            &mut RallySpeed,
+            &mut Transform,
+        ),
+        (With<Ball>, With<OutOfPlay>),
+    >,
+    mut events: EventReader<ScoredEvent>,
+    paddles: Query<(Entity, &Side), (With<Paddle>, Without<GutterPaddle>)>,
+    mut countdown: ResMut<ServeCountdown>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<RngResource>,
+) {
+    // Here we read the events using an EventReader. Only the last point
+    // matters for deciding who gets served to.
+    let Some(event) = events.read().last() else {
+        return;
+    };
+
+    for (entity, mut position, mut previous_position, mut velocity, mut speed, mut transform) in
+        &mut balls
+    {
+        // Serve toward whoever just lost the point
+        let loser = event.side.opposite();
+
+        position.0 = Vec2::ZERO;
+        // Otherwise the next sweep would run from the edge of the screen
+        // back to the center and could hit a paddle on the way
+        previous_position.0 = Vec2::ZERO;
+        velocity.0 = Vec2::ZERO;
+        speed.0 = config.ball_speed;
+        // Sync the transform now rather than waiting for `project_positions`
+        // next frame so the ball doesn't flash at its old position
+        transform.translation = position.0.extend(BALL_Z);
+
+        let mut ball = commands.entity(entity);
+        // A ball can score before it's even been served, if something knocks
+        // it out of the court during the countdown
+        ball.remove::<(OutOfPlay, AwaitingServe)>().insert(Spin(0.));
+
+        // Or hand it to the loser to serve themselves, if they have a paddle
+        // to serve from. `hold_ball` moves it over there.
+        let holder = paddles.iter().find(|(_, side)| **side == loser);
+        if let (true, Some((paddle, _))) = (config.serve_from_paddle, holder) {
+            ball.insert(HeldBall {
+                paddle,
+                secs_held: 0.,
+            });
+        } else {
+            let serve = serve_ball(loser.direction(), config.serve_spread, &mut rng.0);
+            ball.insert(AwaitingServe(serve.0));
+            countdown.0.reset();
+        }
+    }
+}
+
+// Keeps each held ball just in front of the paddle holding it
+fn hold_ball(
+    mut balls: Query<(&mut Position, &mut PreviousPosition, &Shape, &HeldBall), With<Ball>>,
+    paddles: Query<(&Position, &Shape, &Side), (With<Paddle>, Without<Ball>)>,
+) {
+    for (mut position, mut previous_position, ball_shape, held) in &mut balls {
+        let Ok((paddle_position, paddle_shape, side)) = paddles.get(held.paddle) else {
+            continue;
+        };
+
+        let offset = (paddle_shape.0.x + ball_shape.0.x) / 2. + HELD_BALL_GAP;
+        position.0 = paddle_position.0 - Vec2::X * side.direction() * offset;
+        // Carried rather than moved, so there's nothing to sweep for
+        previous_position.0 = position.0;
+    }
+}
+
+// Lets go of held balls: the player's when they press space (or the south
+// button on the gamepad), player two's on D, and the AI's after a moment.
+// Moving the paddle as it lets go sends the ball off at an angle that way.
+fn launch_held_balls(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    time: Res<Time>,
+    mut balls: Query<(Entity, &mut HeldBall), With<Ball>>,
+    paddles: Query<(&Velocity, &Side, Has<Player>, Has<PlayerTwo>), With<Paddle>>,
+) {
+    let gamepad_pressed = gamepads.iter().next().is_some_and(|gamepad| {
+        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::South))
+    });
+
+    for (entity, mut held) in &mut balls {
+        held.secs_held += time.delta_seconds();
+        let Ok((paddle_velocity, side, is_player, is_player_two)) = paddles.get(held.paddle) else {
+            continue;
+        };
+
+        let launch = if is_player {
+            keyboard_input.just_pressed(KeyCode::Space) || gamepad_pressed
+        } else if is_player_two {
+            keyboard_input.just_pressed(KeyCode::D)
+        } else {
+            held.secs_held >= AI_SERVE_SECS
+        };
+        if !launch {
+            continue;
+        }
+
+        let angle = paddle_velocity.0.y.clamp(-1., 1.) * MAX_BOUNCE_ANGLE;
+        let heading = Vec2::new(-side.direction() * angle.cos(), angle.sin());
+        commands.entity(entity).remove::<HeldBall>().insert(AwaitingServe(heading));
+    }
+}
+
+fn move_paddles(
+    mut paddle: Query<
+        (
+            &mut Position,
+            &mut PreviousPosition,
+            &mut Velocity,
+            &TargetVelocity,
+            &Shape,
+            Has<GutterPaddle>,
+        ),
+        With<Paddle>,
+    >,
+    court: Res<CourtDimensions>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    time_scale: Res<TimeScale>,
+) {
+    // This runs on the fixed timestep, so `time` is the length of a step
+    let step = time.delta_seconds() * time_scale.0;
+    let max_change = config.paddle_acceleration * step;
+
+    for (mut position, mut previous_position, mut velocity, target, shape, gutter_paddle) in
+        &mut paddle
+    {
+        velocity.0 = approach(velocity.0, target.0, max_change);
+
+        previous_position.0 = position.0;
+        let new_position = position.0 + velocity.0 * config.paddle_speed * step;
+        if gutter_paddle {
+            position.0.x = clamp_paddle_x(new_position.x, court.width, shape.0.x);
+            position.0.y = new_position.y;
+        } else {
+            position.0.x = new_position.x;
+            // Clamp rather than refusing the move, so the paddle can go all
+            // the way up to the gutter instead of stopping a step short. Each
+            // paddle can be a different height, so go by its own shape.
+            position.0.y =
+                clamp_paddle_y(new_position.y, court.height, shape.0.y, config.gutter_height);
+        }
+
+        // A paddle pressed up against a gutter isn't going anywhere, and
+        // shouldn't put spin on the ball as if it were
+        if position.0.x != new_position.x {
+            velocity.0.x = 0.;
+        }
+        if position.0.y != new_position.y {
+            velocity.0.y = 0.;
+        }
+    }
+}
+
+// Moves `current` toward `target` by no more than `max_change`
+fn approach(current: Vec2, target: Vec2, max_change: f32) -> Vec2 {
+    let difference = target - current;
+    if difference.length() <= max_change {
+        target
+    } else {
+        current + difference.normalize() * max_change
+    }
+}
+
+// Keeps a paddle centered at `y` between the gutters, so that its top and
+// bottom edges stop exactly at the inner edge of each gutter
+fn clamp_paddle_y(y: f32, court_height: f32, paddle_height: f32, gutter_height: f32) -> f32 {
+    let max_y = (court_height / 2. - gutter_height - paddle_height / 2.).max(0.);
+    y.clamp(-max_y, max_y)
+}
+
+// Keeps a gutter paddle centered at `x` between the lines the end paddles
+// move along
+fn clamp_paddle_x(x: f32, court_width: f32, paddle_width: f32) -> f32 {
+    let max_x = (court_width / 2. - PADDLE_PADDING - paddle_width / 2.).max(0.);
+    x.clamp(-max_x, max_x)
+}
+
+// Keeps everything fitted to the court when it changes size: the gutters stay
+// flush with the top and bottom edges and the paddles stay anchored to the sides
+fn resize_court(
+    court: Res<CourtDimensions>,
+    mut gutters: Query<(&mut Position, &mut Shape, &mut Mesh2dHandle), With<Gutter>>,
+    mut paddles: Query<
+        (&mut Position, &mut PreviousPosition, &Shape, &Side, Has<GutterPaddle>),
+        (With<Paddle>, Without<Gutter>),
+    >,
+    mut walls: Query<
+        (&mut Position, &mut Shape, &mut Mesh2dHandle, &Side),
+        (With<Wall>, Without<Gutter>, Without<Paddle>),
+    >,
+    mut score_walls: Query<
+        (&mut Position, &mut Shape, &Side, &ScoreWall),
+        (Without<Gutter>, Without<Paddle>, Without<Wall>),
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    config: Res<GameConfig>,
+) {
+    let half_width = court.width / 2.;
+    let half_height = court.height / 2.;
+
+    for (mut position, mut gutter_shape, mut mesh) in &mut gutters {
+        let gutter_height = gutter_shape.0.y;
+        position.0.y = (half_height - gutter_height / 2.) * position.0.y.signum();
+        gutter_shape.0.x = court.width;
+        *mesh = meshes.add(Mesh::from(shape::Quad::new(gutter_shape.0))).into();
+    }
+
+    for (mut position, mut previous_position, shape, side, gutter_paddle) in &mut paddles {
+        if gutter_paddle {
+            position.0.x = clamp_paddle_x(position.0.x, court.width, shape.0.x);
+            position.0.y = side.gutter_paddle_y(court.height, config.gutter_height);
+        } else {
+            position.0.x = side.paddle_x(court.width);
+
+            // If the court got shorter the paddle may now be inside a gutter
+            position.0.y =
+                clamp_paddle_y(position.0.y, court.height, shape.0.y, config.gutter_height);
+        }
+
+        // Jump straight there rather than sliding over from the old spot
+        previous_position.0 = position.0;
+    }
+
+    for (mut position, mut wall_shape, mut mesh, side) in &mut walls {
+        position.0.x = (half_width - WALL_WIDTH / 2.) * side.direction();
+        wall_shape.0.y = court.height;
+        *mesh = meshes.add(Mesh::from(shape::Quad::new(wall_shape.0))).into();
+    }
+
+    for (mut position, mut wall_shape, side, score_wall) in &mut score_walls {
+        (position.0, wall_shape.0) = score_wall.placement(*side, &court);
+    }
+}
+
+// How far in the camera zooms at match point, as a fraction of the normal
+// view, and how many seconds it takes to get there
+const MATCH_POINT_ZOOM: f32 = 0.1;
+const MATCH_POINT_ZOOM_SECS: f32 = 1.5;
+
+// How long a screen shake lasts, and how far it throws the camera (in pixels)
+// for each unit of ball speed
+const SHAKE_SECS: f32 = 0.2;
+const SHAKE_PER_BALL_SPEED: f32 = 0.01;
+
+// Where a shadow sits relative to its ball, how much it's squashed into an
+// ellipse, and how dark it is
+const SHADOW_OFFSET: Vec3 = Vec3::new(3., -4., -0.2);
+const SHADOW_SCALE: Vec3 = Vec3::new(1.2, 0.6, 1.);
+const SHADOW_COLOR: Color = Color::rgba(0., 0., 0., 0.4);
+
+// How opaque a trail dot starts out, before it fades
+const TRAIL_ALPHA: f32 = 0.4;
+
+// How big impact particles are, how fast they fly off (in pixels per second)
+// and how long they last
+const PARTICLE_SIZE: f32 = 3.;
+const PARTICLE_SPEED: RangeInclusive<f32> = 80.0..=220.0;
+const PARTICLE_SECS: f32 = 0.3;
+
+// How often a power-up turns up, how many can be waiting on the court at once,
+// how big they are and how long their effect lasts
+const POWER_UP_INTERVAL_SECS: f32 = 8.;
+const MAX_POWER_UPS: usize = 2;
+const POWER_UP_SIZE: f32 = 20.;
+const POWER_UP_SECS: f32 = 5.;
+
+// How far apart the dots of the ball path are, and how many there are at most
+const BALL_PATH_SPACING: f32 = 15.;
+const BALL_PATH_DOTS: usize = 80;
+
+// The dots are all spawned up front and moved into place each frame. Any that
+// aren't needed are hidden.
+fn spawn_ball_path(mut commands: Commands) {
+    for _ in 0..BALL_PATH_DOTS {
+        commands.spawn((
+            BallPathDot,
+            Gameplay,
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(3.)),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn show_ball_path(
+    mut dots: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<BallPathDot>>,
+    balls: Query<(&Position, &Velocity, Option<&AwaitingServe>), (With<Ball>, Without<OutOfPlay>)>,
+    obstacles: Query<&Position, Or<(With<Paddle>, With<Wall>)>>,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+    mode: Res<GameMode>,
+) {
+    let mut points = Vec::new();
+
+    if let (true, GameMode::Practice) = (config.show_ball_path, *mode) {
+        if let Some((ball_position, velocity, serve)) = balls.iter().next() {
+            let velocity = ball_heading(velocity, serve);
+            // Trace up to whatever the ball will reach next on the side it's
+            // heading for
+            let target_x = obstacles
+                .iter()
+                .map(|position| position.0.x)
+                .filter(|x| (x - ball_position.0.x) * velocity.x > 0.)
+                .min_by(|a, b| {
+                    let a_distance = (a - ball_position.0.x).abs();
+                    let b_distance = (b - ball_position.0.x).abs();
+                    a_distance.total_cmp(&b_distance)
+                });
+
+            if let Some(target_x) = target_x {
+                let bounce_y = court.height / 2. - config.gutter_height - config.ball_size / 2.;
+                let path = ball_path(ball_position.0, velocity, target_x, bounce_y);
+                points = points_along(&path, BALL_PATH_SPACING, BALL_PATH_DOTS);
+            }
+        }
+    }
+
+    let mut points = points.into_iter();
+    for (mut transform, mut sprite, mut visibility) in &mut dots {
+        if let Some(point) = points.next() {
+            transform.translation = point.extend(BALL_PATH_Z);
+            sprite.color = config.ball_path_color;
+            *visibility = Visibility::Visible;
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+// The corners of the ball's path from `position` until it reaches `target_x`,
+// bouncing between `-bounce_y` and `bounce_y` on the way
+fn ball_path(position: Vec2, velocity: Vec2, target_x: f32, bounce_y: f32) -> Vec<Vec2> {
+    let mut points = vec![position];
+    if (target_x - position.x) * velocity.x <= 0. {
+        return points;
+    }
+
+    let mut point = position;
+    let mut direction = velocity;
+    // A steep enough ball could bounce a huge number of times, so give up
+    // after a few
+    for _ in 0..16 {
+        let to_target = (target_x - point.x) / direction.x;
+        let to_gutter = if direction.y > 0. {
+            (bounce_y - point.y) / direction.y
+        } else if direction.y < 0. {
+            (-bounce_y - point.y) / direction.y
+        } else {
+            f32::INFINITY
+        };
+
+        if to_target <= to_gutter {
+            points.push(point + direction * to_target);
+            break;
+        }
+
+        point += direction * to_gutter.max(0.);
+        points.push(point);
+        direction.y = -direction.y;
+    }
+
+    points
+}
+
+// Evenly spaced points along a path of straight lines, up to `max` of them
+fn points_along(path: &[Vec2], spacing: f32, max: usize) -> Vec<Vec2> {
+    let mut points = Vec::new();
+    // How far into the current segment the next point goes
+    let mut offset = 0.;
+
+    for segment in path.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+
+        while offset <= length && points.len() < max {
+            points.push(start.lerp(end, offset / length));
+            offset += spacing;
+        }
+        offset -= length;
+    }
+
+    points
+}
+
+// Runs after `project_positions` so shadows line up with where their balls
+// are drawn rather than where they were last frame
+fn follow_ball_shadows(
+    mut commands: Commands,
+    mut shadows: Query<(Entity, &BallShadow, &mut Transform, &mut Visibility)>,
+    balls: Query<&Transform, (With<Ball>, Without<BallShadow>)>,
+    config: Res<GameConfig>,
+) {
+    for (entity, shadow, mut transform, mut visibility) in &mut shadows {
+        let Ok(ball_transform) = balls.get(shadow.0) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        transform.translation = ball_transform.translation + SHADOW_OFFSET;
+        *visibility = if config.ball_shadow {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn spawn_trail(
+    mut commands: Commands,
+    balls: Query<(&Position, &PreviousPosition, &Handle<ColorMaterial>), With<Ball>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut mesh: Local<Option<(f32, Handle<Mesh>)>>,
+) {
+    if config.trail_length <= 0. {
+        return;
+    }
+
+    let radius = config.ball_size / 3.;
+    let mesh = match &*mesh {
+        Some((made_for, handle)) if *made_for == radius => handle.clone(),
+        // Made again whenever the ball changes size
+        _ => {
+            let handle = meshes.add(Mesh::from(shape::Circle::new(radius)));
+            *mesh = Some((radius, handle.clone()));
+            handle
+        }
+    };
+
+    for (position, previous_position, ball_material) in &balls {
+        // Nothing to leave behind while the ball is waiting to be served
+        if position.0 == previous_position.0 {
+            continue;
+        }
+
+        let color = materials
+            .get(ball_material)
+            .map_or(theme.ball(), |material| material.color);
+
+        commands.spawn((
+            TrailFade(Timer::from_seconds(config.trail_length, TimerMode::Once)),
+            Gameplay,
+            MaterialMesh2dBundle {
+                mesh: mesh.clone().into(),
+                // Each dot fades on its own, so needs its own material
+                material: materials.add(ColorMaterial::from(color.with_a(TRAIL_ALPHA))),
+                transform: Transform::from_translation(previous_position.0.extend(TRAIL_Z)),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn fade_trail(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut trail: Query<(Entity, &mut TrailFade, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, mut fade, material) in &mut trail {
+        fade.0.tick(time.delta());
+
+        if fade.0.finished() {
+            commands.entity(entity).despawn();
+        } else if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(TRAIL_ALPHA * fade.0.percent_left());
+        }
+    }
+}
+
+fn burst_on_paddle_hits(
+    mut events: EventReader<BallCollisionEvent>,
+    balls: Query<&Position, With<Ball>>,
+    paddles: Query<(&Handle<ColorMaterial>, &Shape), With<Paddle>>,
+    materials: Res<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut particles: ImpactParticles,
+) {
+    for event in events.read() {
+        let Ok((paddle_material, paddle_shape)) = paddles.get(event.entity) else {
+            continue;
+        };
+        if Bounce::new(event.collision, Some(paddle_shape)) != Bounce::Paddle {
+            continue;
+        }
+        let Ok(position) = balls.get(event.ball) else {
+            continue;
+        };
+
+        // The particles come off in the color of the paddle that was hit
+        let color = materials
+            .get(paddle_material)
+            .map_or(theme.ball(), |material| material.color);
+        particles.spawn(position.0, color, config.impact_particles);
+    }
+}
+
+// What it takes to throw out a burst of particles: somewhere to spawn them
+// and the generator that picks which way each one goes
+#[derive(SystemParam)]
+struct ImpactParticles<'w, 's> {
+    commands: Commands<'w, 's>,
+    rng: ResMut<'w, CosmeticRng>,
+}
+
+impl ImpactParticles<'_, '_> {
+    // Throws `count` particles out from `position` in random directions
+    fn spawn(&mut self, position: Vec2, color: Color, count: usize) {
+        for _ in 0..count {
+            let angle = self.rng.0.gen_range(0.0..std::f32::consts::TAU);
+            let speed = self.rng.0.gen_range(PARTICLE_SPEED);
+
+            self.commands.spawn((
+                Particle(Vec2::from_angle(angle) * speed),
+                Gameplay,
+                ParticleLifetime(Timer::from_seconds(PARTICLE_SECS, TimerMode::Once)),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(PARTICLE_Z)),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &Particle, &mut ParticleLifetime, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, particle, mut lifetime, mut transform, mut sprite) in &mut particles {
+        lifetime.0.tick(time.delta());
+
+        if lifetime.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            transform.translation += (particle.0 * time.delta_seconds()).extend(0.);
+            sprite.color.set_a(lifetime.0.percent_left());
+        }
+    }
+}
+
+fn reset_power_ups(mut spawner: ResMut<PowerUpSpawner>) {
+    *spawner = PowerUpSpawner::default();
+}
+
+fn spawn_power_ups(
+    mut commands: Commands,
+    mut spawner: ResMut<PowerUpSpawner>,
+    power_ups: Query<(), With<PowerUp>>,
+    court: Res<CourtDimensions>,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<RngResource>,
+) {
+    if !config.power_ups || !spawner.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    if power_ups.iter().len() >= MAX_POWER_UPS {
+        return;
+    }
+
+    // Somewhere in the middle of the court, well clear of the paddles and
+    // gutters
+    let max_x = court.width / 2. - PADDLE_PADDING * 3.;
+    let max_y = court.height / 2. - config.gutter_height - POWER_UP_SIZE;
+    if max_x <= 0. || max_y <= 0. {
+        return;
+    }
+
+    let rng = &mut rng.0;
+    let kind = POWER_UP_KINDS[rng.gen_range(0..POWER_UP_KINDS.len())];
+    let position = Vec2::new(rng.gen_range(-max_x..=max_x), rng.gen_range(-max_y..=max_y));
+
+    commands.spawn((
+        PowerUp { kind },
+        Gameplay,
+        Position(position),
+        Shape(Vec2::splat(POWER_UP_SIZE)),
+        ZLayer(POWER_UP_Z),
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(POWER_UP_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(POWER_UP_Z)),
+            ..default()
+        },
+    ));
+}
+
+// A ball that runs through a power-up changes for a while. Its shape changes
+// along with how it's drawn, so it collides at its new size too, and
+// `move_ball` picks up any change of speed.
+fn collect_power_ups(
+    mut commands: Commands,
+    mut balls: Query<
+        (Entity, &Position, &mut Shape, &mut Transform, &Handle<ColorMaterial>),
+        With<Ball>,
+    >,
+    power_ups: Query<(Entity, &PowerUp, &Position, &Shape), Without<Ball>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+) {
+    for (entity, power_up, position, shape) in &power_ups {
+        let hit = balls.iter_mut().find(|(_, ball_position, ball_shape, _, _)| {
+            collide(ball_position.0.extend(0.), ball_shape.0, position.0.extend(0.), shape.0)
+                .is_some()
+        });
+        let Some((ball, _, mut ball_shape, mut transform, material)) = hit else {
+            continue;
+        };
+
+        let kind = power_up.kind;
+        set_ball_size(&mut ball_shape, &mut transform, config.ball_size, kind.ball_scale());
+        if let Some(material) = materials.get_mut(material) {
+            material.color = kind.color();
+        }
+        // Replacing whatever the ball had before, so effects never stack up.
+        // The clock starts over with the new one.
+        commands.entity(ball).insert(ActivePowerUp {
+            kind,
+            timer: Timer::from_seconds(POWER_UP_SECS, TimerMode::Once),
+        });
+        commands.entity(entity).despawn();
+    }
+}
+
+fn expire_power_ups(
+    mut commands: Commands,
+    mut balls: Query<
+        (Entity, &mut ActivePowerUp, &mut Shape, &mut Transform, &Handle<ColorMaterial>),
+        With<Ball>,
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    config: Res<GameConfig>,
+) {
+    for (ball, mut power_up, mut shape, mut transform, material) in &mut balls {
+        if power_up.timer.tick(time.delta()).finished() {
+            set_ball_size(&mut shape, &mut transform, config.ball_size, 1.);
+            if let Some(material) = materials.get_mut(material) {
+                material.color = theme.ball();
+            }
+            commands.entity(ball).remove::<ActivePowerUp>();
+        }
+    }
+}
+
+// The mesh was built at `size`, so scaling the transform keeps what's drawn
+// the same size as the shape
+fn set_ball_size(shape: &mut Shape, transform: &mut Transform, size: f32, scale: f32) {
+    shape.0 = Vec2::splat(size * scale);
+    transform.scale = Vec3::new(scale, scale, 1.);
+}
+
+// Shifts each ball's color from the theme's toward `ball_fast_color` as its
+// rally speeds up, so it's back to the theme color on every serve. A ball with
+// a power-up keeps the power-up's color instead.
+fn color_balls_by_speed(
+    balls: Query<(&RallySpeed, &Handle<ColorMaterial>), (With<Ball>, Without<ActivePowerUp>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+) {
+    let speed_range = config.max_ball_speed - config.ball_speed;
+
+    for (speed, material) in &balls {
+        let heat = if speed_range > 0. {
+            ((speed.0 - config.ball_speed) / speed_range).clamp(0., 1.)
+        } else {
+            0.
+        };
+
+        if let Some(material) = materials.get_mut(material) {
+            material.color = lerp_color(theme.ball(), config.ball_fast_color, heat);
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    Color::from(from.lerp(to, t))
+}
+
+// Draws everything where it is right now. Physics only steps at a fixed rate,
+// so anything that moves is drawn part way between its last two positions
+// depending on how far we are into the next step, which keeps fast motion
+// smooth on high refresh rate screens.
+fn project_positions(
+    mut things: Query<(&mut Transform, &Position, Option<&PreviousPosition>, Option<&ZLayer>)>,
+    fixed_time: Res<Time<Fixed>>,
+    paused: Res<Paused>,
+) {
+    // Nothing steps while paused, so hold everything at its latest position
+    // instead of letting it wobble with the leftover time
+    let alpha = if paused.0 {
+        1.
+    } else {
+        fixed_time.overstep_percentage()
+    };
+
+    for (mut transform, position, previous_position, z_layer) in &mut things {
+        let drawn_at = match previous_position {
+            Some(previous_position) => previous_position.0.lerp(position.0, alpha),
+            None => position.0,
+        };
+        let z = z_layer.map_or(transform.translation.z, |z_layer| z_layer.0);
+        transform.translation = drawn_at.extend(z);
+    }
+}
+
+fn spawn_ball(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut rng: ResMut<RngResource>,
+) {
+    println!("Spawning ball...");
+
+    // Nobody has lost a point yet, so the opening serve goes either way. After
+    // that `reset_ball` always serves toward whoever lost the last point.
+    let direction = if rng.0.gen() { 1. } else { -1. };
+    let velocity = serve_ball(direction, config.serve_spread, &mut rng.0);
+    spawn_extra_ball(&mut commands, &mut meshes, &mut materials, &config, *theme, velocity.0);
+}
+
+// A serve heading toward the side `direction` points to, at a random angle of
+// up to `spread` radians above or below horizontal
+fn serve_ball(direction: f32, spread: f32, rng: &mut impl Rng) -> Velocity {
+    let angle = rng.gen_range(-spread..=spread);
+    Velocity(Vec2::new(direction.signum() * angle.cos(), angle.sin()))
+}
+
+// Adds another ball at the center of the court, to be served along `heading`
+// as soon as the serve countdown allows. Everything that moves, bounces or
+// scores balls handles any number of them.
+fn spawn_extra_ball(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    config: &GameConfig,
+    theme: Theme,
+    heading: Vec2,
+) {
+    let mesh = Mesh::from(shape::Circle::new(config.ball_size / 2.0));
+    let material = ColorMaterial::from(theme.ball());
+
+    // Now our mesh shape is derived from the `Shape` we made as a new component
+    let mesh_handle = meshes.add(mesh);
+    let material_handle = materials.add(material);
+
+    let ball = commands
+        .spawn((
+            BallBundle::new(config.ball_speed, config.ball_size),
+            AwaitingServe(heading),
+            Gameplay,
+            MaterialMesh2dBundle {
+                mesh: mesh_handle.clone().into(),
+                material: material_handle,
+                ..default()
+            },
+        ))
+        .id();
+
+    // Starts out hidden, `follow_ball_shadows` shows it if shadows are on
+    commands.spawn((
+        BallShadow(ball),
+        Gameplay,
+        MaterialMesh2dBundle {
+            mesh: mesh_handle.into(),
+            material: materials.add(ColorMaterial::from(SHADOW_COLOR)),
+            transform: Transform::from_translation(SHADOW_OFFSET).with_scale(SHADOW_SCALE),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+// Debug key for trying out multi-ball: B drops another ball into play
+fn spawn_extra_ball_on_key(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut rng: ResMut<RngResource>,
+) {
+    if keyboard_input.just_pressed(KeyCode::B) {
+        let direction = if rng.0.gen() { 1. } else { -1. };
+        let velocity = serve_ball(direction, config.serve_spread, &mut rng.0);
+
+        spawn_extra_ball(&mut commands, &mut meshes, &mut materials, &config, *theme, velocity.0);
+    }
+}
+
+// Where the meshes and colors of everything drawn on the court are kept
+#[derive(SystemParam)]
+struct ShapeAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+}
+
+fn spawn_paddles(
+    mut commands: Commands,
+    assets: ShapeAssets,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+    mode: Res<GameMode>,
+    player_side: Res<PlayerSide>,
+    theme: Res<Theme>,
+) {
+    let ShapeAssets {
+        mut meshes,
+        mut materials,
+    } = assets;
+    println!("Spawning paddles...");
+
+    for side in [Side::Left, Side::Right] {
+        let color = match side {
+            Side::Left => theme.left_paddle(),
+            Side::Right => theme.right_paddle(),
+        };
+        let material = materials.add(ColorMaterial::from(color));
+        let is_player = side == player_side.0;
+
+        // Practice and survival put a wall across the far end instead
+        if !is_player && mode.has_wall() {
+            let wall_size = Vec2::new(WALL_WIDTH, court.height);
+            let wall_x = (court.width / 2. - WALL_WIDTH / 2.) * side.direction();
+            commands.spawn((
+                Wall,
+                Gameplay,
+                side,
+                CollisionLayer::Wall,
+                ZLayer(GUTTER_Z),
+                Shape(wall_size),
+                Position(Vec2::new(wall_x, 0.)),
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(Mesh::from(shape::Quad::new(wall_size))).into(),
+                    material,
+                    ..default()
+                },
+            ));
+            continue;
+        }
+
+        let size = config.paddle_size(side);
+        if mode.has_gutter_paddles() {
+            // The same paddle turned on its side
+            let size = Vec2::new(size.y, size.x);
+            let y = side.gutter_paddle_y(court.height, config.gutter_height);
+            let mut gutter_paddle = commands.spawn((
+                PaddleBundle::new(side, 0., y, size),
+                GutterPaddle,
+                Gameplay,
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(Mesh::from(shape::Quad::new(size))).into(),
+                    material: material.clone(),
+                    ..default()
+                },
+            ));
+            if !is_player {
+                gutter_paddle.insert(AiAim::default());
+            }
+        }
+
+        let mut paddle = commands.spawn((
+            PaddleBundle::new(side, side.paddle_x(court.width), 0., size),
+            Gameplay,
+            MaterialMesh2dBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad::new(size))).into(),
+                material,
+                ..default()
+            },
+        ));
+
+        match (*mode, is_player) {
+            // In a demo match the AI in the player's place always plays its
+            // best, so whatever difficulty is picked gets measured against
+            // the same thing
+            (GameMode::DemoMatch, true) => paddle.insert((Ai, AiAim::default(), Difficulty::Hard)),
+            (_, true) => paddle.insert(Player),
+            (GameMode::TwoPlayer, false) => paddle.insert(PlayerTwo),
+            // Adding the Ai component here so we can query for this specific
+            // paddle in our `move_ai_paddle` system
+            (_, false) => paddle.insert((Ai, AiAim::default())),
+        };
+    }
+}
+
+fn spawn_score_walls(
+    mut commands: Commands,
+    court: Res<CourtDimensions>,
+    mode: Res<GameMode>,
+    player_side: Res<PlayerSide>,
+) {
+    for side in [Side::Left, Side::Right] {
+        // There's a wall in the way at the far end, so a ball can only get
+        // past it by glitching through, and that's not a point
+        let has_end = !(mode.has_wall() && side != player_side.0);
+        // With gutter paddles, going off the top or bottom is a point against
+        // whoever was guarding that gutter
+        let has_gutter = mode.has_gutter_paddles();
+
+        for (score_wall, wanted) in [(ScoreWall::End, has_end), (ScoreWall::Gutter, has_gutter)] {
+            if wanted {
+                let (position, size) = score_wall.placement(side, &court);
+                commands.spawn((score_wall, side, Gameplay, Position(position), Shape(size)));
+            }
+        }
+    }
+}
+
+fn spawn_gutters(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+) {
+    println!("Spawning gutters...");
+
+    let gutter_height = config.gutter_height;
+    let top_gutter_y = court.height / 2. - gutter_height / 2.;
+    let bottom_gutter_y = -court.height / 2. + gutter_height / 2.;
+
+    let top_gutter = GutterBundle::new(0., top_gutter_y, court.width, gutter_height);
+    let bottom_gutter = GutterBundle::new(0., bottom_gutter_y, court.width, gutter_height);
+    let mesh = meshes.add(Mesh::from(shape::Quad::new(top_gutter.shape.0)));
+    let material = materials.add(ColorMaterial::from(theme.gutter()));
+
+    commands.spawn((
+        top_gutter,
+        MaterialMesh2dBundle {
+            mesh: mesh.clone().into(),
+            material: material.clone(),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        bottom_gutter,
+        MaterialMesh2dBundle {
+            mesh: mesh.clone().into(),
+            material: material.clone(),
+            ..default()
+        },
+    ));
+}
+
+// Recolors what's already on screen to match the theme. Balls and paddles
+// aren't around outside of play, and get the theme's colors when they spawn.
+fn apply_theme(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    gutters: Query<&Handle<ColorMaterial>, With<Gutter>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ClearColor(theme.background()));
+
+    for material in &gutters {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = theme.gutter();
+        }
+    }
+}
+
+// Resizes the gutters when their thickness is changed in the settings, and
+// moves everything lined up against them to match. Thicker gutters leave a
+// tighter court.
+fn apply_gutter_height(
+    mut commands: Commands,
+    mut gutters: Query<(&mut Position, &mut Shape, &mut Mesh2dHandle), With<Gutter>>,
+    mut paddles: Query<
+        (&mut Position, &mut PreviousPosition, &Shape, &Side, Has<GutterPaddle>),
+        (With<Paddle>, Without<Gutter>),
+    >,
+    dashes: Query<Entity, With<CenterLine>>,
+    court: Res<CourtDimensions>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    config: Res<GameConfig>,
+) {
+    let gutter_height = config.gutter_height;
+    // Most settings have nothing to do with the gutters
+    if gutters.iter().all(|(_, shape, _)| shape.0.y == gutter_height) {
+        return;
+    }
+    for (mut position, mut shape, mut mesh) in &mut gutters {
+        shape.0.y = gutter_height;
+        position.0.y = (court.height / 2. - gutter_height / 2.) * position.0.y.signum();
+        *mesh = meshes.add(Mesh::from(shape::Quad::new(shape.0))).into();
+    }
+
+    for (mut position, mut previous_position, shape, side, gutter_paddle) in &mut paddles {
+        position.0.y = if gutter_paddle {
+            side.gutter_paddle_y(court.height, gutter_height)
+        } else {
+            clamp_paddle_y(position.0.y, court.height, shape.0.y, gutter_height)
+        };
+        previous_position.0 = position.0;
+    }
+
+    for dash in &dashes {
+        commands.entity(dash).despawn();
+    }
+    spawn_center_dashes(&mut commands, court.height, gutter_height);
+}
+
+fn spawn_center_line(
+    mut commands: Commands,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+) {
+    spawn_center_dashes(&mut commands, court.height, config.gutter_height);
+}
+
+// The number of dashes depends on the court height, so rather than moving the
+// old ones around we replace the whole line
+fn reposition_center_line(
+    mut commands: Commands,
+    court: Res<CourtDimensions>,
+    dashes: Query<Entity, With<CenterLine>>,
+    config: Res<GameConfig>,
+) {
+    for dash in &dashes {
+        commands.entity(dash).despawn();
+    }
+    spawn_center_dashes(&mut commands, court.height, config.gutter_height);
+}
+
+// Spreads dashes evenly down the middle of the court between the gutters,
+// with each dash taking up half of its share of the height
+fn spawn_center_dashes(commands: &mut Commands, court_height: f32, gutter_height: f32) {
+    // Only the part between the gutters gets dashes
+    let court_height = (court_height - 2. * gutter_height).max(0.);
+    let dash_count = (court_height / CENTER_LINE_SPACING).floor() as u32;
+    if dash_count == 0 {
+        return;
+    }
+
+    let spacing = court_height / dash_count as f32;
+    let dash_size = Vec2::new(CENTER_LINE_WIDTH, spacing / 2.);
+    let top = court_height / 2.;
+
+    for i in 0..dash_count {
+        let y = top - spacing * (i as f32 + 0.5);
+        commands.spawn((
+            CenterLine,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: CENTER_LINE_COLOR,
+                    custom_size: Some(dash_size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., y, CENTER_LINE_Z),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn spawn_scoreboard(
     mut commands: Commands,
 ) {
     commands.spawn((
-        PlayerScoreboard,
+        RightScoreText,
         // Create a TextBundle that has a Text with a single section.
         TextBundle::from_section(
             // Accepts a `String` or any type that converts into a `String`, such as `&str`
@@ -447,32 +4081,1624 @@ fn spawn_scoreboard(
         .with_style(Style {
             position_type: PositionType::Absolute,
             top: Val::Px(5.0),
-            right: Val::Px(15.0),
+            // A quarter of the way in puts each score over its half of the court
+            right: Val::Percent(25.0),
+            ..default()
+        })
+    ));
+
+    commands.spawn((
+        LeftScoreText,
+        TextBundle::from_section(
+            "0",
+            TextStyle {
+                font_size: 72.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Percent(25.0),
+            ..default()
+        })
+    ));
+
+}
+
+fn spawn_countdown_text(mut commands: Commands) {
+    commands.spawn((
+        CountdownText,
+        Gameplay,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 96.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            align_self: AlignSelf::Center,
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+    ));
+}
+
+// Between the two scores at the top of the screen, in timed mode only
+fn spawn_match_timer_text(mut commands: Commands, mode: Res<GameMode>) {
+    if *mode != GameMode::Timed {
+        return;
+    }
+
+    commands.spawn((
+        MatchTimerText,
+        Gameplay,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            justify_self: JustifySelf::Center,
+            ..default()
+        }),
+    ));
+}
+
+// Under each score, empty until a side wins two points in a row
+fn spawn_streak_text(mut commands: Commands) {
+    for side in [Side::Left, Side::Right] {
+        let mut style = Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(85.0),
+            ..default()
+        };
+        match side {
+            Side::Left => style.left = Val::Percent(25.0),
+            Side::Right => style.right = Val::Percent(25.0),
+        }
+
+        commands.spawn((
+            StreakText(side),
+            Gameplay,
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(style),
+        ));
+    }
+}
+
+// Size of each life icon and the gap between them
+const LIFE_ICON_SIZE: f32 = 12.;
+
+// A row of balls in the top left corner, one per life, in survival mode only
+fn spawn_life_icons(mut commands: Commands, mode: Res<GameMode>, theme: Res<Theme>) {
+    if *mode != GameMode::Survival {
+        return;
+    }
+
+    for life in 0..STARTING_LIVES {
+        commands.spawn((
+            LifeIcon(life),
+            Gameplay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(30.),
+                    left: Val::Px(10. + life as f32 * LIFE_ICON_SIZE * 2.),
+                    width: Val::Px(LIFE_ICON_SIZE),
+                    height: Val::Px(LIFE_ICON_SIZE),
+                    ..default()
+                },
+                background_color: theme.ball().into(),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn show_life_icons(lives: Res<Lives>, mut icons: Query<(&LifeIcon, &mut Visibility)>) {
+    if !lives.is_changed() {
+        return;
+    }
+
+    for (icon, mut visibility) in &mut icons {
+        *visibility = if icon.0 < lives.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Clears away everything left over from a match. Also run on leaving the game
+// over screen, in case anything was spawned on the way out of play.
+fn cleanup_gameplay(commands: Commands, query: Query<Entity, With<Gameplay>>) {
+    despawn_with(commands, query);
+}
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Sounds {
+        paddle_hit: asset_server.load("sounds/paddle_hit.wav"),
+        wall_hit: asset_server.load("sounds/wall_hit.wav"),
+        score: asset_server.load("sounds/score.wav"),
+    });
+}
+
+// Plays a one-off sound, unless it couldn't be loaded in which case the game
+// just carries on silently. Nothing plays at all when the volume is off.
+fn play_sound(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    sound: &Handle<AudioSource>,
+    volume: Volume,
+) {
+    if volume.is_muted() {
+        return;
+    }
+    if let Some(LoadState::Failed) = asset_server.get_load_state(sound) {
+        return;
+    }
+
+    commands.spawn(AudioBundle {
+        source: sound.clone(),
+        settings: PlaybackSettings::DESPAWN
+            .with_volume(bevy::audio::Volume::new_relative(volume.0)),
+    });
+}
+
+fn play_score_sound(
+    mut commands: Commands,
+    mut events: EventReader<ScoredEvent>,
+    asset_server: Res<AssetServer>,
+    sounds: Res<Sounds>,
+    volume: Res<Volume>,
+) {
+    for _ in events.read() {
+        play_sound(&mut commands, &asset_server, &sounds.score, *volume);
+    }
+}
+
+fn trigger_screen_shake(
+    mut events: EventReader<BallCollisionEvent>,
+    balls: Query<&RallySpeed, With<Ball>>,
+    paddles: Query<&Shape, With<Paddle>>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    for event in events.read() {
+        if Bounce::new(event.collision, paddles.get(event.entity).ok()) != Bounce::Paddle {
+            continue;
+        }
+
+        // Faster hits shake harder
+        if let Ok(speed) = balls.get(event.ball) {
+            shake.magnitude = speed.0 * SHAKE_PER_BALL_SPEED;
+            shake.timer = Timer::from_seconds(SHAKE_SECS, TimerMode::Once);
+        }
+    }
+}
+
+// How long the gamepad rumbles for when the player returns the ball
+const RUMBLE_SECS: f32 = 0.1;
+
+// Rumbles the gamepad driving the player's paddle each time it returns the
+// ball, harder the faster the ball was going. Nothing happens on the keyboard.
+fn rumble_on_player_hits(
+    mut events: EventReader<BallCollisionEvent>,
+    balls: Query<&RallySpeed, With<Ball>>,
+    players: Query<&Shape, (With<Paddle>, With<Player>)>,
+    gamepads: Res<Gamepads>,
+    config: Res<GameConfig>,
+    mut rumble: EventWriter<GamepadRumbleRequest>,
+) {
+    // Same gamepad `gamepad_direction` reads from
+    let Some(gamepad) = gamepads.iter().next() else {
+        events.clear();
+        return;
+    };
+
+    for event in events.read() {
+        if Bounce::new(event.collision, players.get(event.entity).ok()) != Bounce::Paddle {
+            continue;
+        }
+
+        if let Ok(speed) = balls.get(event.ball) {
+            let strength = (speed.0 / config.max_ball_speed).clamp(0., 1.);
+            rumble.send(GamepadRumbleRequest::Add {
+                gamepad,
+                duration: Duration::from_secs_f32(RUMBLE_SECS),
+                intensity: GamepadRumbleIntensity::strong_motor(strength),
+            });
+        }
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<ScreenShake>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    mut rng: ResMut<CosmeticRng>,
+) {
+    shake.timer.tick(time.delta());
+
+    for mut transform in &mut camera {
+        if shake.timer.finished() {
+            // Snap straight back so the camera can't drift off center
+            transform.translation.x = 0.;
+            transform.translation.y = 0.;
+        } else {
+            let direction = Vec2::new(rng.0.gen_range(-1. ..=1.), rng.0.gen_range(-1. ..=1.));
+            let offset = direction * shake.magnitude * shake.timer.percent_left();
+            transform.translation.x = offset.x;
+            transform.translation.y = offset.y;
+        }
+    }
+}
+
+// Eases the camera in while a rally could decide the match, the same rallies
+// that are slowed down, and back out once the point's over. It never zooms
+// so far that a paddle at the edge of its reach would leave the screen.
+fn zoom_on_match_point(
+    mut camera: Query<&mut OrthographicProjection, With<Camera2d>>,
+    score: Res<Score>,
+    countdown: Res<ServeCountdown>,
+    court: Res<CourtDimensions>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let deciding = countdown.0.finished() && is_match_point(&score, &config);
+    let target = if config.match_point_zoom && deciding {
+        let half_court = court.size() / 2.;
+        let paddle_reach = Vec2::new(
+            half_court.x - PADDLE_PADDING + config.paddle_width,
+            half_court.y - config.gutter_height,
+        );
+        (1. - MATCH_POINT_ZOOM).max((paddle_reach / half_court).max_element())
+    } else {
+        1.
+    };
+
+    let max_change = MATCH_POINT_ZOOM / MATCH_POINT_ZOOM_SECS * time.delta_seconds();
+    for mut projection in &mut camera {
+        let change = (target - projection.scale).clamp(-max_change, max_change);
+        // Only write on a change so the projection isn't rebuilt every frame
+        if change != 0. {
+            projection.scale += change;
+        }
+    }
+}
+
+// Menus are always seen at the normal zoom, whatever the match was doing
+fn reset_camera_zoom(mut camera: Query<&mut OrthographicProjection, With<Camera2d>>) {
+    for mut projection in &mut camera {
+        projection.scale = 1.;
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn_empty().insert(Camera2dBundle::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        build_test_app, enter_state, skip_serve_countdown, start_match, TEST_FRAME,
+    };
+    use bevy::time::TimeUpdateStrategy;
+
+    #[test]
+    fn clamp_paddle_y_stops_at_the_inner_edge_of_each_gutter() {
+        let (window_height, paddle_height, gutter_height) = (600., 50., 20.);
+        let inner_edge = window_height / 2. - gutter_height;
+
+        let top = clamp_paddle_y(1000., window_height, paddle_height, gutter_height);
+        assert_eq!(top + paddle_height / 2., inner_edge);
+
+        let bottom = clamp_paddle_y(-1000., window_height, paddle_height, gutter_height);
+        assert_eq!(bottom - paddle_height / 2., -inner_edge);
+    }
+
+    #[test]
+    fn paddles_move_within_a_court_size_set_without_a_window() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        let window = app.world.query_filtered::<Entity, With<Window>>().single(&app.world);
+        app.world.despawn(window);
+        app.insert_resource(CourtDimensions {
+            width: 400.,
+            height: 200.,
+        });
+        start_match(&mut app);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Up);
+        for _ in 0..30 {
+            app.update();
+        }
+
+        let config = app.world.resource::<GameConfig>();
+        let top = 100. - config.gutter_height - config.paddle_height / 2.;
+        let mut player = app.world.query_filtered::<&Position, With<Player>>();
+        assert_eq!(player.single(&app.world).0, Vec2::new(200. - PADDLE_PADDING, top));
+    }
+
+    #[test]
+    fn court_keeps_its_shape_in_a_wide_window() {
+        let mut app = build_test_app(Vec2::new(1600., 600.));
+        start_match(&mut app);
+
+        assert_eq!(app.world.resource::<CourtDimensions>().size(), Vec2::new(800., 600.));
+        let mut player = app.world.query_filtered::<&Position, With<Player>>();
+        assert_eq!(player.single(&app.world).0.x, 400. - PADDLE_PADDING);
+
+        // Stretched to fill the window when the shape isn't fixed
+        app.world.resource_mut::<GameConfig>().court_aspect = Vec2::ZERO;
+        app.update();
+        assert_eq!(app.world.resource::<CourtDimensions>().size(), Vec2::new(1600., 600.));
+    }
+
+    #[test]
+    fn thicker_gutters_close_in_on_the_paddles() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        // Up against the top gutter at its usual thickness
+        let mut paddles = app.world.query_filtered::<&mut Position, With<Paddle>>();
+        for mut position in paddles.iter_mut(&mut app.world) {
+            position.0.y = 300. - 20. - 25.;
+        }
+
+        app.world.resource_mut::<GameConfig>().gutter_height = 60.;
+        app.update();
+
+        let mut gutters = app.world.query_filtered::<(&Position, &Shape), With<Gutter>>();
+        for (position, shape) in gutters.iter(&app.world) {
+            assert_eq!(shape.0.y, 60.);
+            assert_eq!(position.0.y.abs(), 300. - 30.);
+        }
+        for position in paddles.iter(&app.world) {
+            assert_eq!(position.0.y, 300. - 60. - 25.);
+        }
+    }
+
+    #[test]
+    fn going_off_either_end_scores_for_the_other_side() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        // Moved there outright, so the sweep doesn't run into the paddle on the
+        // way out
+        let mut balls =
+            app.world.query_filtered::<(&mut Position, &mut PreviousPosition), With<Ball>>();
+        let (mut position, mut previous_position) = balls.single_mut(&mut app.world);
+        position.0 = Vec2::new(-401., 0.);
+        previous_position.0 = position.0;
+        app.update();
+        assert_eq!(app.world.resource::<Score>().right, 1);
+
+        let mut paddles = app.world.query_filtered::<(&Position, &Side), With<Paddle>>();
+        for (position, side) in paddles.iter(&app.world) {
+            assert_eq!(position.0.x.signum(), side.direction());
+        }
+    }
+
+    #[test]
+    fn four_paddles_score_through_the_gutters() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::FourPaddles;
+        start_match(&mut app);
+        assert_eq!(app.world.query::<&Paddle>().iter(&app.world).count(), 4);
+
+        let mut gutter_paddles =
+            app.world.query_filtered::<(&Position, &Side), With<GutterPaddle>>();
+        for (position, side) in gutter_paddles.iter(&app.world) {
+            assert_eq!(position.0.y.signum(), side.gutter_direction());
+        }
+
+        // The left side guards the top, so the ball going through it is a
+        // point for the right
+        let mut balls =
+            app.world.query_filtered::<(&mut Position, &mut PreviousPosition), With<Ball>>();
+        let (mut position, mut previous_position) = balls.single_mut(&mut app.world);
+        position.0 = Vec2::new(0., 301.);
+        previous_position.0 = position.0;
+        app.update();
+        assert_eq!(app.world.resource::<Score>().right, 1);
+    }
+
+    #[test]
+    fn gutter_paddles_aim_the_ball_across_the_court() {
+        let mut velocity = Velocity(Vec2::new(0., 1.));
+        let paddle = (&Position(Vec2::new(0., 255.)), &Shape(Vec2::new(50., 10.)), None, true);
+        let bounce = bounce_ball(
+            &mut velocity,
+            Vec2::new(20., 245.),
+            Collision::Bottom,
+            paddle,
+            &GameConfig::default(),
+        );
+
+        // Sent back down, and off to the right for hitting right of center
+        assert!(bounce == Bounce::Paddle);
+        assert!(velocity.0.y < 0.);
+        assert!(velocity.0.x > 0.);
+        assert!((velocity.0.length() - 1.).abs() < 1e-5);
+    }
+
+    // Where the balls were each frame of the first few seconds of a match
+    fn ball_positions_with_seed(seed: u64) -> Vec<Vec2> {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(RngResource::seeded(seed));
+        start_match(&mut app);
+
+        let mut positions = Vec::new();
+        let mut balls = app.world.query_filtered::<&Position, With<Ball>>();
+        for _ in 0..600 {
+            app.update();
+            positions.extend(balls.iter(&app.world).map(|position| position.0));
+        }
+        positions
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        let first = ball_positions_with_seed(7);
+        assert_eq!(first, ball_positions_with_seed(7));
+        assert_ne!(first, ball_positions_with_seed(8));
+    }
+
+    #[test]
+    fn player_can_take_the_left_paddle() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(PlayerSide(Side::Left));
+        start_match(&mut app);
+
+        let mut player = app.world.query_filtered::<&Side, With<Player>>();
+        assert_eq!(*player.single(&app.world), Side::Left);
+        let mut ai = app.world.query_filtered::<&Side, With<Ai>>();
+        assert_eq!(*ai.single(&app.world), Side::Right);
+    }
+
+    #[test]
+    fn ai_only_reacts_to_the_ball_every_so_often() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(Difficulty::Easy);
+        start_match(&mut app);
+
+        // Still waiting to be served, so the ball only moves when told to
+        let mut balls =
+            app.world.query_filtered::<(&mut Position, &mut AwaitingServe), With<Ball>>();
+        let (_, mut serve) = balls.single_mut(&mut app.world);
+        serve.0 = Vec2::new(-1., 0.);
+        let mut ai = app.world.query::<&mut AiAim>();
+        ai.single_mut(&mut app.world).reaction = Timer::default();
+        app.update();
+        let aim = ai.single(&app.world);
+        assert_eq!(aim.target_y, Some(aim.offset));
+
+        // Well within the easy AI's reaction time, so it hasn't noticed
+        let (mut position, _) = balls.single_mut(&mut app.world);
+        position.0.y = 200.;
+        for _ in 0..5 {
+            app.update();
+        }
+        let aim = ai.single(&app.world);
+        assert_eq!(aim.target_y, Some(aim.offset));
+
+        let frames = (Difficulty::Easy.reaction_secs() / TEST_FRAME.as_secs_f32()).ceil() as u32;
+        for _ in 0..frames {
+            app.update();
+        }
+        let aim = ai.single(&app.world);
+        assert_eq!(aim.target_y, Some(200. + aim.offset));
+    }
+
+    #[test]
+    fn ai_settles_on_its_target_instead_of_jittering() {
+        assert_eq!(ai_chase_speed(2., 3., 1.), 0.);
+        assert_eq!(ai_chase_speed(-3., 3., 1.), 0.);
+        assert_eq!(ai_chase_speed(-AI_EASE_DISTANCE * 2., 3., 0.8), -0.8);
+
+        // Slows down as it closes in
+        let halfway = ai_chase_speed(AI_EASE_DISTANCE / 2., 3., 1.);
+        assert!(halfway > ai_chase_speed(AI_EASE_DISTANCE / 4., 3., 1.));
+        assert!(halfway < 1.);
+    }
+
+    #[test]
+    fn handicap_only_shrinks_the_chosen_paddle() {
+        let config = GameConfig {
+            handicap: Some(Side::Right),
+            ..default()
+        };
+        assert_eq!(config.paddle_size(Side::Left).y, config.paddle_height);
+        assert_eq!(config.paddle_size(Side::Right).y, config.paddle_height * HANDICAP_SCALE);
+        assert_eq!(config.paddle_size(Side::Right).x, config.paddle_width);
+    }
+
+    #[test]
+    fn clamp_paddle_y_leaves_paddles_between_the_gutters_alone() {
+        assert_eq!(clamp_paddle_y(0., 600., 50., 20.), 0.);
+        assert_eq!(clamp_paddle_y(-120., 600., 50., 20.), -120.);
+        assert_eq!(clamp_paddle_y(255., 600., 50., 20.), 255.);
+    }
+
+    const PADDLE_SIZE: Vec2 = Vec2::new(10., 50.);
+    const GUTTER_SIZE: Vec2 = Vec2::new(100., 10.);
+
+    // Which side of an obstacle at the origin a ball at the given position has
+    // hit, as `handle_collisions` sees it
+    fn side_hit(ball_position: Vec2, size: Vec2) -> Collision {
+        let ball_size = Vec2::new(BALL_WIDTH, BALL_WIDTH);
+        overlap_collision(ball_position, ball_size, Vec2::ZERO, size).unwrap().1
+    }
+
+    #[test]
+    fn hitting_the_left_side_sends_the_ball_back_left() {
+        let collision = side_hit(Vec2::new(-8., 0.), PADDLE_SIZE);
+        assert_eq!(collision, Collision::Left);
+        assert_eq!(reflect_velocity(Vec2::new(1., 0.5), collision), Vec2::new(-1., 0.5));
+    }
+
+    #[test]
+    fn hitting_the_right_side_sends_the_ball_back_right() {
+        let collision = side_hit(Vec2::new(8., 0.), PADDLE_SIZE);
+        assert_eq!(collision, Collision::Right);
+        assert_eq!(reflect_velocity(Vec2::new(-1., 0.5), collision), Vec2::new(1., 0.5));
+    }
+
+    #[test]
+    fn hitting_the_top_sends_the_ball_back_up() {
+        let collision = side_hit(Vec2::new(0., 8.), GUTTER_SIZE);
+        assert_eq!(collision, Collision::Top);
+        assert_eq!(reflect_velocity(Vec2::new(0.5, -1.), collision), Vec2::new(0.5, 1.));
+    }
+
+    #[test]
+    fn hitting_the_bottom_sends_the_ball_back_down() {
+        let collision = side_hit(Vec2::new(0., -8.), GUTTER_SIZE);
+        assert_eq!(collision, Collision::Bottom);
+        assert_eq!(reflect_velocity(Vec2::new(0.5, 1.), collision), Vec2::new(0.5, -1.));
+    }
+
+    #[test]
+    fn a_ball_inside_an_obstacle_bounces_off_the_nearest_side() {
+        let ball_size = Vec2::new(BALL_WIDTH, BALL_WIDTH);
+        let (resolved, collision) =
+            overlap_collision(Vec2::new(0., 20.), ball_size, Vec2::ZERO, PADDLE_SIZE).unwrap();
+        assert_eq!(resolved, Vec2::new(0., 30.));
+        assert_eq!(reflect_velocity(Vec2::new(1., -1.), collision), Vec2::new(1., 1.));
+    }
+
+    #[test]
+    fn a_ball_heading_away_is_left_alone() {
+        let collision = side_hit(Vec2::new(-8., 0.), PADDLE_SIZE);
+        assert!(!heading_into(Vec2::new(-1., 0.5), collision));
+        assert_eq!(reflect_velocity(Vec2::new(-1., 0.5), collision), Vec2::new(-1., 0.5));
+    }
+
+    #[test]
+    fn a_ball_clear_of_the_obstacle_does_not_collide() {
+        let ball_size = Vec2::new(BALL_WIDTH, BALL_WIDTH);
+        let clear = |ball_position, size| {
+            overlap_collision(ball_position, ball_size, Vec2::ZERO, size).is_none()
+        };
+        assert!(clear(Vec2::new(50., 0.), PADDLE_SIZE));
+        assert!(clear(Vec2::new(0., 11.), GUTTER_SIZE));
+    }
+
+    #[test]
+    fn approach_steps_toward_the_target_without_overshooting() {
+        let halfway = approach(Vec2::ZERO, Vec2::new(0., 1.), 0.5);
+        assert_eq!(halfway, Vec2::new(0., 0.5));
+        assert_eq!(approach(halfway, Vec2::new(0., 1.), 0.75), Vec2::new(0., 1.));
+        assert_eq!(approach(Vec2::new(0., 1.), Vec2::ZERO, 0.25), Vec2::new(0., 0.75));
+    }
+
+    #[test]
+    fn eject_ball_pushes_a_ball_out_of_the_side_of_a_paddle() {
+        let (paddle_position, paddle_size) = (Vec2::new(300., 0.), Vec2::new(10., 50.));
+        let ball_size = Vec2::new(BALL_WIDTH, BALL_WIDTH);
+
+        let (ejected, collision) =
+            eject_ball(Vec2::new(298., 5.), ball_size, paddle_position, paddle_size);
+        assert_eq!(collision, Collision::Left);
+        assert_eq!(ejected, Vec2::new(290., 5.));
+        assert!(collide(
+            ejected.extend(0.),
+            ball_size,
+            paddle_position.extend(0.),
+            paddle_size
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn eject_ball_pushes_a_ball_out_of_the_end_of_a_paddle() {
+        let (paddle_position, paddle_size) = (Vec2::new(300., 0.), Vec2::new(10., 50.));
+        let ball_size = Vec2::new(BALL_WIDTH, BALL_WIDTH);
+
+        let (ejected, collision) =
+            eject_ball(Vec2::new(300., 28.), ball_size, paddle_position, paddle_size);
+        assert_eq!(collision, Collision::Top);
+        assert_eq!(ejected, Vec2::new(300., 30.));
+    }
+
+    #[test]
+    fn add_paddle_spin_steers_the_ball_the_way_the_paddle_moves() {
+        let mut velocity = Velocity(Vec2::new(-2., 0.));
+        add_paddle_spin(&mut velocity, 1., 0.3);
+        assert!(velocity.0.x < 0.);
+        assert!(velocity.0.y > 0.);
+        assert!((velocity.0.length() - 2.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn add_paddle_spin_never_goes_past_the_steepest_bounce() {
+        let mut velocity = Velocity(Vec2::new(1., 0.5));
+        add_paddle_spin(&mut velocity, 1., 100.);
+        let angle = velocity.0.y.atan2(velocity.0.x);
+        assert!(angle <= MAX_BOUNCE_ANGLE + 1e-5);
+    }
+
+    #[test]
+    fn is_match_point_when_either_side_is_one_point_from_winning() {
+        let score = |left, right| Score { left, right };
+        let config = GameConfig::default();
+        assert!(!is_match_point(&score(0, 0), &config));
+        assert!(!is_match_point(&score(WINNING_SCORE - 2, WINNING_SCORE - 2), &config));
+        assert!(is_match_point(&score(WINNING_SCORE - 1, 0), &config));
+        assert!(is_match_point(&score(3, WINNING_SCORE - 1), &config));
+    }
+
+    #[test]
+    fn is_lost_only_past_twice_the_window_or_not_a_number() {
+        let window_size = Vec2::new(800., 600.);
+        assert!(!is_lost(Vec2::new(450., 0.), window_size));
+        assert!(!is_lost(Vec2::new(-790., 590.), window_size));
+        assert!(is_lost(Vec2::new(801., 0.), window_size));
+        assert!(is_lost(Vec2::new(0., -601.), window_size));
+        assert!(is_lost(Vec2::new(f32::NAN, 0.), window_size));
+    }
+
+    #[test]
+    fn predict_ball_landing_follows_a_straight_line_without_bounces() {
+        let landing = predict_ball_landing(Vec2::ZERO, Vec2::new(-2., 1.), -100., 100.);
+        assert_eq!(landing, 50.);
+    }
+
+    #[test]
+    fn predict_ball_landing_accounts_for_gutter_bounces() {
+        // Off the top at x = 100 and back down to y = 50
+        assert_eq!(predict_ball_landing(Vec2::ZERO, Vec2::ONE, 150., 100.), 50.);
+        // Then off the bottom at x = 300 and back up to y = -50
+        assert_eq!(predict_ball_landing(Vec2::ZERO, Vec2::ONE, 350., 100.), -50.);
+    }
+
+    #[test]
+    fn ending_a_rally_only_raises_the_longest_rally() {
+        let mut stats = MatchStats {
+            current_rally: 7,
+            longest_rally: 0,
             ..default()
-        })
-    ));
+        };
+        stats.end_rally();
+        assert_eq!((stats.current_rally, stats.longest_rally), (0, 7));
 
-    commands.spawn((
-        AiScoreboard,
-        TextBundle::from_section(
-            "0",
-            TextStyle {
-                font_size: 72.0,
-                color: Color::WHITE,
-                ..default()
+        stats.current_rally = 3;
+        stats.end_rally();
+        assert_eq!((stats.current_rally, stats.longest_rally), (0, 7));
+    }
+
+    #[test]
+    fn sample_replay_blends_between_the_nearest_frames() {
+        let (ball, paddle) = (Entity::from_raw(1), Entity::from_raw(2));
+        let frames = VecDeque::from([
+            vec![(ball, Vec2::ZERO), (paddle, Vec2::new(300., 0.))],
+            vec![(ball, Vec2::new(10., 4.)), (paddle, Vec2::new(300., 2.))],
+        ]);
+
+        let sampled = sample_replay(&frames, 0.5);
+        assert_eq!(sampled, vec![(ball, Vec2::new(5., 2.)), (paddle, Vec2::new(300., 1.))]);
+
+        assert_eq!(sample_replay(&frames, 1.), frames[1]);
+        assert!(sample_replay(&frames, 2.).is_empty());
+    }
+
+    #[test]
+    fn ball_path_bends_at_each_gutter_on_the_way() {
+        let path = ball_path(Vec2::ZERO, Vec2::ONE, 350., 100.);
+        assert_eq!(
+            path,
+            vec![
+                Vec2::ZERO,
+                Vec2::new(100., 100.),
+                Vec2::new(300., -100.),
+                Vec2::new(350., -50.),
+            ]
+        );
+    }
+
+    #[test]
+    fn ball_path_stops_where_it_is_when_heading_away() {
+        let path = ball_path(Vec2::ZERO, Vec2::new(-1., 0.), 350., 100.);
+        assert_eq!(path, vec![Vec2::ZERO]);
+    }
+
+    #[test]
+    fn points_along_spaces_points_evenly_round_corners() {
+        let path = [Vec2::ZERO, Vec2::new(15., 0.), Vec2::new(15., 15.)];
+        let points = points_along(&path, 10., 10);
+        assert_eq!(
+            points,
+            vec![Vec2::ZERO, Vec2::new(10., 0.), Vec2::new(15., 5.), Vec2::new(15., 15.)]
+        );
+
+        assert_eq!(points_along(&path, 10., 2).len(), 2);
+    }
+
+    #[test]
+    fn rebinding_to_a_key_in_use_swaps_the_two_actions() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(InputAction::Up, KeyCode::W);
+        assert_eq!(bindings.key(InputAction::Up), KeyCode::W);
+
+        bindings.rebind(InputAction::Down, KeyCode::W);
+        assert_eq!(bindings.key(InputAction::Down), KeyCode::W);
+        assert_eq!(bindings.key(InputAction::Up), KeyCode::Down);
+        assert_eq!(bindings.key(InputAction::Pause), KeyCode::Escape);
+    }
+
+    #[test]
+    fn loser_serves_from_their_paddle() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<GameConfig>().serve_from_paddle = true;
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        // The right side scores, so the AI on the left holds the ball
+        let mut balls =
+            app.world.query_filtered::<(&mut Position, &mut PreviousPosition), With<Ball>>();
+        let (mut position, mut previous_position) = balls.single_mut(&mut app.world);
+        position.0 = Vec2::new(-450., 0.);
+        previous_position.0 = position.0;
+        app.update();
+        app.update();
+
+        let config = app.world.resource::<GameConfig>();
+        let offset = (config.paddle_width + BALL_WIDTH) / 2. + HELD_BALL_GAP;
+        let held_x = Side::Left.paddle_x(800.) + offset;
+        let mut balls = app.world.query_filtered::<(&Position, &Velocity), With<Ball>>();
+        let (position, velocity) = balls.single(&app.world);
+        assert_eq!(position.0.x, held_x);
+        assert_eq!(velocity.0, Vec2::ZERO);
+
+        let frames = (AI_SERVE_SECS / TEST_FRAME.as_secs_f32()).ceil() as u32 + 5;
+        for _ in 0..frames {
+            app.update();
+        }
+        let (position, velocity) = balls.single(&app.world);
+        assert!(velocity.0.x > 0.);
+        assert!(position.0.x > held_x);
+        assert_eq!(app.world.query::<&HeldBall>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn holding_a_rebound_key_moves_the_player_paddle() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<InputBindings>().rebind(InputAction::Up, KeyCode::I);
+        start_match(&mut app);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::I);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let mut player = app.world.query_filtered::<&Position, With<Player>>();
+        assert!(player.single(&app.world).0.y > 0.);
+    }
+
+    // Taps the up key for a single frame, then returns which way the player
+    // is asking to go a couple of frames after letting go
+    fn direction_after_a_tap(input_buffer_secs: f32) -> f32 {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<GameConfig>().input_buffer_secs = input_buffer_secs;
+        start_match(&mut app);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Up);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().release(KeyCode::Up);
+        app.update();
+        app.update();
+
+        let mut player = app.world.query_filtered::<&TargetVelocity, With<Player>>();
+        player.single(&app.world).0.y
+    }
+
+    #[test]
+    fn input_buffer_holds_onto_a_tap_for_a_moment() {
+        assert_eq!(direction_after_a_tap(0.), 0.);
+        assert_eq!(direction_after_a_tap(0.1), 1.);
+    }
+
+    #[test]
+    fn mouse_mode_holds_the_paddle_still_without_a_cursor() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<InputMode>() = InputMode::Mouse;
+        start_match(&mut app);
+
+        // The keys don't do anything in mouse mode either
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Up);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let mut player = app.world.query_filtered::<&Position, With<Player>>();
+        assert_eq!(player.single(&app.world).0.y, 0.);
+    }
+
+    #[test]
+    fn ball_shadows_leave_play_along_with_their_balls() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        app.update();
+
+        let mut shadows = app.world.query::<&BallShadow>();
+        assert_eq!(shadows.iter(&app.world).count(), 1);
+
+        enter_state(&mut app, GameState::MainMenu);
+        assert_eq!(shadows.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn losing_focus_pauses_the_match() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        let window = app.world.query_filtered::<Entity, With<Window>>().single(&app.world);
+        app.world.send_event(WindowFocused {
+            window,
+            focused: false,
+        });
+        app.update();
+        assert!(app.world.resource::<Paused>().0);
+
+        // Only comes back by itself if the setting says so
+        app.world.send_event(WindowFocused {
+            window,
+            focused: true,
+        });
+        app.update();
+        assert!(app.world.resource::<Paused>().0);
+    }
+
+    #[test]
+    fn practice_puts_a_wall_where_the_left_paddle_would_be() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::Practice;
+        start_match(&mut app);
+
+        let paddles = app.world.query::<&Paddle>().iter(&app.world).count();
+        assert_eq!(paddles, 1);
+
+        let mut walls = app.world.query_filtered::<(&Position, &Shape), With<Wall>>();
+        let (position, shape) = walls.single(&app.world);
+        assert_eq!(position.0.x - shape.0.x / 2., -400.);
+        assert_eq!(shape.0.y, 600.);
+    }
+
+    #[test]
+    fn serve_arrow_shows_until_the_ball_is_served() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        app.update();
+
+        let mut arrows = app.world.query_filtered::<&Transform, With<ServeArrow>>();
+        let mut balls = app.world.query_filtered::<&AwaitingServe, With<Ball>>();
+        let serve = balls.single(&app.world).0.normalize();
+        let arrow = arrows.single(&app.world).translation.truncate();
+        assert!((arrow.normalize() - serve).length() < 1e-4);
+
+        skip_serve_countdown(&mut app);
+        assert_eq!(arrows.iter(&app.world).count(), 0);
+        assert_eq!(balls.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn survival_ends_when_the_last_life_is_lost() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::Survival;
+        start_match(&mut app);
+        assert_eq!(app.world.query::<&LifeIcon>().iter(&app.world).count(), 3);
+
+        for lives_left in (0..STARTING_LIVES).rev() {
+            app.world.send_event(ScoredEvent { side: Side::Left });
+            app.update();
+            assert_eq!(app.world.resource::<Lives>().0, lives_left);
+        }
+
+        app.update();
+        assert_eq!(*app.world.resource::<State<GameState>>().get(), GameState::GameOver);
+    }
+
+    #[test]
+    fn three_points_in_a_row_earn_a_bonus_point() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        for side in [Side::Left, Side::Left, Side::Right, Side::Left, Side::Left, Side::Left] {
+            app.world.send_event(ScoredEvent { side });
+            app.update();
+        }
+
+        // The right side's point broke the first streak, so only the last
+        // three in a row earn the bonus
+        assert_eq!(app.world.resource::<Score>().left, 6);
+        assert_eq!(app.world.resource::<Score>().right, 1);
+        assert_eq!(app.world.resource::<MatchStats>().streak_of(Side::Left), 3);
+        assert_eq!(app.world.resource::<MatchStats>().streak_of(Side::Right), 0);
+    }
+
+    #[test]
+    fn ball_moves_in_proportion_to_frame_time() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        let mut balls = app.world.query_filtered::<&Position, With<Ball>>();
+        let start = balls.single(&app.world).0;
+        app.update();
+        let short_frame = balls.single(&app.world).0 - start;
+
+        let start = balls.single(&app.world).0;
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(TEST_FRAME * 2));
+        app.update();
+        let long_frame = balls.single(&app.world).0 - start;
+
+        assert_ne!(short_frame, Vec2::ZERO);
+        assert!((long_frame - short_frame * 2.).length() < 1e-3);
+    }
+
+    #[test]
+    fn leaving_play_cleans_up_after_the_match() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.update();
+        let in_menu = app.world.entities().len();
+
+        for _ in 0..2 {
+            start_match(&mut app);
+            // Play a little before leaving
+            for _ in 0..30 {
+                app.update();
+            }
+            enter_state(&mut app, GameState::MainMenu);
+            assert_eq!(app.world.entities().len(), in_menu);
+        }
+    }
+
+    #[test]
+    fn start_delay_holds_the_first_serve() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<GameConfig>().start_delay_secs = 1.;
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        let mut balls = app.world.query_filtered::<&Position, With<Ball>>();
+        assert_eq!(balls.single(&app.world).0, Vec2::ZERO);
+
+        let delay = (1. / TEST_FRAME.as_secs_f32()).ceil() as u32;
+        for _ in 0..delay {
+            app.update();
+        }
+        assert_ne!(balls.single(&app.world).0, Vec2::ZERO);
+    }
+
+    #[test]
+    fn ball_covers_its_speed_in_pixels_per_second() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        let mut balls = app.world.query_filtered::<&Position, With<Ball>>();
+        let start = balls.single(&app.world).0;
+        app.update();
+        let moved = (balls.single(&app.world).0 - start).length();
+
+        let step = app.world.resource::<Time<Fixed>>().timestep().as_secs_f32();
+        let expected = app.world.resource::<GameConfig>().ball_speed * step;
+        assert!((moved - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn volume_steps_stop_at_silent_and_full() {
+        let mut volume = Volume(0.3);
+        for _ in 0..5 {
+            volume = volume.step(-1.);
+        }
+        assert_eq!(volume, Volume(0.));
+        assert!(volume.is_muted());
+
+        for _ in 0..15 {
+            volume = volume.step(1.);
+        }
+        assert_eq!(volume, Volume(1.));
+    }
+
+    #[test]
+    fn muted_game_plays_no_sounds() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(Volume(0.));
+        start_match(&mut app);
+
+        app.world.send_event(ScoredEvent { side: Side::Left });
+        app.update();
+
+        let sounds = app.world.query::<&Handle<AudioSource>>().iter(&app.world).count();
+        assert_eq!(sounds, 0);
+    }
+
+    #[test]
+    fn match_stats_add_up_and_start_over_each_match() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        let ball = app.world.query_filtered::<Entity, With<Ball>>().single(&app.world);
+        let paddle = app.world.query_filtered::<Entity, With<Paddle>>().iter(&app.world).next();
+        app.world.send_event(BallCollisionEvent {
+            ball,
+            entity: paddle.unwrap(),
+            collision: Collision::Left,
+        });
+        for _ in 0..30 {
+            app.update();
+        }
+
+        let stats = app.world.resource::<MatchStats>();
+        assert_eq!(stats.paddle_hits, 1);
+        assert!(stats.match_secs > 0.);
+        assert!(stats.top_speed >= app.world.resource::<GameConfig>().ball_speed);
+
+        enter_state(&mut app, GameState::MainMenu);
+        start_match(&mut app);
+        let stats = app.world.resource::<MatchStats>();
+        assert_eq!(stats.paddle_hits, 0);
+    }
+
+    #[test]
+    fn paddle_hits_burst_into_particles_that_fade_away() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        let ball = app.world.query_filtered::<Entity, With<Ball>>().single(&app.world);
+        let paddle = app.world.query_filtered::<Entity, With<Paddle>>().iter(&app.world).next();
+        app.world.send_event(BallCollisionEvent {
+            ball,
+            entity: paddle.unwrap(),
+            collision: Collision::Left,
+        });
+        app.update();
+
+        let mut particles = app.world.query::<&ParticleLifetime>();
+        let expected = app.world.resource::<GameConfig>().impact_particles;
+        assert_eq!(particles.iter(&app.world).count(), expected);
+
+        let frames = (PARTICLE_SECS / TEST_FRAME.as_secs_f32()).ceil() as u32 + 1;
+        for _ in 0..frames {
+            app.update();
+        }
+        assert_eq!(particles.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn balls_only_run_into_each_other_when_configured_to() {
+        for balls_collide in [false, true] {
+            let mut app = build_test_app(Vec2::new(800., 600.));
+            app.world.resource_mut::<GameConfig>().balls_collide = balls_collide;
+            start_match(&mut app);
+
+            // Overlapping the served ball and heading into it
+            let mut second_ball = BallBundle::new(300., BALL_WIDTH);
+            second_ball.velocity = Velocity(Vec2::new(-1., 0.));
+            second_ball.position = Position(Vec2::new(5., 0.));
+            second_ball.previous_position = PreviousPosition(Vec2::new(5., 0.));
+            app.world.spawn(second_ball);
+            app.update();
+
+            let events = app.world.resource::<Events<BallCollisionEvent>>();
+            let hits = events.get_reader().read(events).count();
+            assert_eq!(hits > 0, balls_collide);
+        }
+    }
+
+    #[test]
+    fn power_ups_resize_the_ball_until_they_wear_off() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        // Right where the ball is waiting to be served
+        app.world.spawn((
+            PowerUp {
+                kind: PowerUpKind::Big,
             },
-        )
-        .with_text_alignment(TextAlignment::Center)
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            left: Val::Px(15.0),
+            Position(Vec2::ZERO),
+            Shape(Vec2::splat(POWER_UP_SIZE)),
+        ));
+        app.update();
+
+        let mut balls = app.world.query_filtered::<&Shape, With<Ball>>();
+        assert_eq!(balls.single(&app.world).0, Vec2::splat(BALL_WIDTH * 2.));
+        assert_eq!(app.world.query::<&PowerUp>().iter(&app.world).count(), 0);
+
+        let frames = (POWER_UP_SECS / TEST_FRAME.as_secs_f32()).ceil() as u32 + 1;
+        for _ in 0..frames {
+            app.update();
+        }
+        assert_eq!(balls.single(&app.world).0, Vec2::splat(BALL_WIDTH));
+    }
+
+    #[test]
+    fn speed_power_ups_replace_each_other_rather_than_stacking() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        for kind in [PowerUpKind::Fast, PowerUpKind::Fast, PowerUpKind::Slow] {
+            let shape = Shape(Vec2::splat(POWER_UP_SIZE));
+            app.world.spawn((PowerUp { kind }, Position(Vec2::ZERO), shape));
+            app.update();
+        }
+
+        let mut balls = app.world.query_filtered::<&ActivePowerUp, With<Ball>>();
+        assert_eq!(balls.single(&app.world).kind, PowerUpKind::Slow);
+    }
+
+    #[test]
+    fn demo_match_puts_the_ai_on_both_paddles() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::DemoMatch;
+        start_match(&mut app);
+
+        let mut ai_paddles = app.world.query_filtered::<(), (With<Paddle>, With<Ai>)>();
+        assert_eq!(ai_paddles.iter(&app.world).count(), 2);
+        let mut players = app.world.query_filtered::<(), With<Player>>();
+        assert_eq!(players.iter(&app.world).count(), 0);
+    }
+
+    // Plays a timed match right up to the end with the given score, then lets
+    // the clock run out
+    fn finish_timed_match(left: u32, right: u32) -> App {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::Timed;
+        start_match(&mut app);
+
+        *app.world.resource_mut::<Score>() = Score { left, right };
+        app.world
+            .resource_mut::<MatchTimer>()
+            .0
+            .set_elapsed(Duration::from_secs_f32(MATCH_SECS - 0.001));
+        app.update();
+        app.update();
+        app
+    }
+
+    #[test]
+    fn timed_match_goes_to_whoever_leads_when_time_is_up() {
+        let app = finish_timed_match(2, 3);
+        assert_eq!(*app.world.resource::<State<GameState>>().get(), GameState::GameOver);
+        assert_eq!(*app.world.resource::<Winner>(), Winner::Right);
+    }
+
+    #[test]
+    fn timed_match_level_at_the_end_is_a_draw() {
+        let app = finish_timed_match(4, 4);
+        assert_eq!(*app.world.resource::<State<GameState>>().get(), GameState::GameOver);
+        assert_eq!(*app.world.resource::<Winner>(), Winner::Draw);
+    }
+
+    // Sends a ball flat across the court toward the player's paddle, which
+    // waits some way above it, and returns how steeply it's rising after a
+    // few steps
+    fn ball_slope_toward_raised_player(assist: bool) -> f32 {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(AssistMode(assist));
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        let mut balls = app.world.query_filtered::<(&mut Position, &mut Velocity), With<Ball>>();
+        let (mut position, mut velocity) = balls.single_mut(&mut app.world);
+        position.0 = Vec2::ZERO;
+        velocity.0 = Vec2::new(1., 0.);
+        let mut player = app.world.query_filtered::<&mut Position, With<Player>>();
+        player.single_mut(&mut app.world).0.y = 100.;
+
+        for _ in 0..10 {
+            app.update();
+        }
+        let (_, velocity) = balls.single(&app.world);
+        velocity.0.y / velocity.0.x
+    }
+
+    #[test]
+    fn assist_mode_bends_the_ball_toward_the_player() {
+        assert_eq!(ball_slope_toward_raised_player(false), 0.);
+        assert!(ball_slope_toward_raised_player(true) > 0.);
+    }
+
+    #[test]
+    fn spin_curves_the_ball_and_wears_off() {
+        let mut velocity = Velocity(Vec2::new(1., 0.));
+        let mut spin = Spin(0.5);
+        curve_ball(&mut velocity, &mut spin, 0.1);
+
+        // Counter-clockwise, so a ball heading right starts to rise
+        assert!(velocity.0.y > 0.);
+        assert!(spin.0 < 0.5);
+        assert!((velocity.0.length() - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn paddles_only_spin_the_ball_in_curve_ball_mode() {
+        for curve_ball in [false, true] {
+            let mut app = build_test_app(Vec2::new(800., 600.));
+            app.world.resource_mut::<GameConfig>().curve_ball = curve_ball;
+            start_match(&mut app);
+
+            let mut paddles = app.world.query_filtered::<(Entity, &mut Velocity), With<Player>>();
+            let (paddle, mut paddle_velocity) = paddles.single_mut(&mut app.world);
+            paddle_velocity.0.y = 1.;
+            let mut balls = app.world.query_filtered::<Entity, With<Ball>>();
+            let ball = balls.single(&app.world);
+            app.world.send_event(BallCollisionEvent {
+                ball,
+                entity: paddle,
+                collision: Collision::Left,
+            });
+            app.update();
+
+            let spin = app.world.get::<Spin>(ball).unwrap().0;
+            assert_eq!(spin != 0., curve_ball);
+        }
+    }
+
+    #[test]
+    fn ball_heats_up_to_the_fast_color_at_top_speed() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        let max_speed = app.world.resource::<GameConfig>().max_ball_speed;
+        let mut balls = app.world.query_filtered::<&mut RallySpeed, With<Ball>>();
+        balls.single_mut(&mut app.world).0 = max_speed;
+        app.update();
+
+        let mut materials = app.world.query_filtered::<&Handle<ColorMaterial>, With<Ball>>();
+        let material = materials.single(&app.world).clone();
+        let color = app.world.resource::<Assets<ColorMaterial>>().get(&material).unwrap().color;
+        let fast_color = app.world.resource::<GameConfig>().ball_fast_color;
+        assert_eq!(color.as_rgba_f32(), fast_color.as_rgba_f32());
+    }
+
+    #[test]
+    fn balls_are_drawn_over_paddles_over_gutters() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        app.update();
+
+        let mut balls = app.world.query_filtered::<&Transform, With<Ball>>();
+        let mut paddles = app.world.query_filtered::<&Transform, With<Paddle>>();
+        let mut gutters = app.world.query_filtered::<&Transform, With<Gutter>>();
+        assert_eq!(balls.single(&app.world).translation.z, BALL_Z);
+        assert!(paddles.iter(&app.world).all(|transform| transform.translation.z == PADDLE_Z));
+        assert!(gutters.iter(&app.world).all(|transform| transform.translation.z == GUTTER_Z));
+    }
+
+    #[test]
+    fn test_app_serves_a_ball_once_the_countdown_is_over() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        let paddles = app.world.query::<&Paddle>().iter(&app.world).count();
+        assert_eq!(paddles, 2);
+
+        let mut balls = app.world.query_filtered::<&Position, With<Ball>>();
+        assert_eq!(balls.single(&app.world).0, Vec2::ZERO);
+
+        skip_serve_countdown(&mut app);
+        assert_ne!(balls.single(&app.world).0, Vec2::ZERO);
+    }
+
+    #[test]
+    fn a_ball_that_jumps_clean_over_a_score_wall_still_scores() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+
+        // From inside the court to well past the score wall behind the right
+        // paddle in a single step, without ever landing on it
+        let mut balls =
+            app.world.query_filtered::<(&mut Position, &mut PreviousPosition), With<Ball>>();
+        let (mut position, mut previous_position) = balls.single_mut(&mut app.world);
+        previous_position.0 = Vec2::new(390., 0.);
+        position.0 = Vec2::new(400. + SCORE_WALL_DEPTH + 50., 0.);
+        app.update();
+        assert_eq!(app.world.resource::<Score>().left, 1);
+    }
+
+    #[test]
+    fn restitution_under_one_slows_the_ball_off_a_paddle() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<GameConfig>().restitution = 0.5;
+        start_match(&mut app);
+
+        let mut paddles = app.world.query_filtered::<Entity, With<Player>>();
+        let paddle = paddles.single(&app.world);
+        let mut balls = app.world.query_filtered::<(Entity, &RallySpeed), With<Ball>>();
+        let (ball, speed) = balls.single(&app.world);
+        let speed_before = speed.0;
+        app.world.send_event(BallCollisionEvent {
+            ball,
+            entity: paddle,
+            collision: Collision::Left,
+        });
+        app.update();
+
+        let config = app.world.resource::<GameConfig>();
+        let expected = (speed_before + config.rally_speed_up) * 0.5;
+        assert_eq!(app.world.get::<RallySpeed>(ball).unwrap().0, expected);
+        assert!(expected < speed_before);
+    }
+
+    #[test]
+    fn ball_sits_still_until_it_is_served() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        start_match(&mut app);
+        app.update();
+
+        let mut balls = app.world.query_filtered::<(&Position, &Velocity), With<Ball>>();
+        let (position, velocity) = balls.single(&app.world);
+        assert_eq!(position.0, Vec2::ZERO);
+        assert_eq!(velocity.0, Vec2::ZERO);
+
+        skip_serve_countdown(&mut app);
+        let (position, velocity) = balls.single(&app.world);
+        assert_ne!(velocity.0, Vec2::ZERO);
+        assert_ne!(position.0, Vec2::ZERO);
+    }
+
+    #[test]
+    fn two_players_split_the_keyboard_with_no_ai() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::TwoPlayer;
+        start_match(&mut app);
+        assert_eq!(app.world.query::<&Ai>().iter(&app.world).count(), 0);
+
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.press(KeyCode::Up);
+        keys.press(KeyCode::S);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let mut player = app.world.query_filtered::<(&Position, &Side), With<Player>>();
+        let (position, side) = player.single(&app.world);
+        assert_eq!((*side, position.0.y > 0.), (Side::Right, true));
+        let mut player_two = app.world.query_filtered::<(&Position, &Side), With<PlayerTwo>>();
+        let (position, side) = player_two.single(&app.world);
+        assert_eq!((*side, position.0.y < 0.), (Side::Left, true));
+    }
+
+    #[test]
+    fn ball_is_drawn_and_collides_at_the_configured_size() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.world.resource_mut::<GameConfig>().ball_size = 24.;
+        start_match(&mut app);
+
+        let mut balls = app.world.query_filtered::<(&Shape, &Mesh2dHandle), With<Ball>>();
+        let (shape, mesh) = balls.single(&app.world);
+        assert_eq!(shape.0, Vec2::splat(24.));
+        let mesh = app.world.resource::<Assets<Mesh>>().get(&mesh.0).unwrap();
+        let drawn = mesh.compute_aabb().unwrap().half_extents * 2.;
+        assert!((Vec2::new(drawn.x, drawn.y) - shape.0).length() < 1e-3);
+    }
+
+    #[test]
+    fn reaching_the_win_target_wins_outright() {
+        let config = GameConfig {
+            win_target: 5,
             ..default()
-        })
-    ));
+        };
+        assert!(!config.has_won(4, 0));
+        assert!(config.has_won(5, 4));
+        assert!(config.has_won(6, 5));
+    }
 
-}
+    #[test]
+    fn win_by_two_plays_on_past_the_target_until_someone_pulls_ahead() {
+        let config = GameConfig {
+            win_target: 5,
+            win_by_two: true,
+            ..default()
+        };
+        assert!(config.has_won(5, 3));
+        assert!(!config.has_won(5, 4));
+        assert!(!config.has_won(6, 5));
+        assert!(config.has_won(7, 5));
 
-fn spawn_camera(mut commands: Commands) {
-    commands.spawn_empty().insert(Camera2dBundle::default());
-}   
\ No newline at end of file
+        // Level at the target, one more point can't win it yet. Only the
+        // side with the advantage is at match point.
+        let deuce = Score { left: 5, right: 5 };
+        assert!(!is_match_point(&deuce, &config));
+        let advantage = Score { left: 6, right: 5 };
+        assert!(is_match_point(&advantage, &config));
+    }
+
+    #[test]
+    fn camera_zooms_in_at_match_point_and_back_out_in_the_menu() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        // A ball that never moves can't end the match while we wait
+        app.world.resource_mut::<GameConfig>().ball_speed = 0.;
+        start_match(&mut app);
+        app.world.resource_mut::<Score>().left = WINNING_SCORE - 1;
+
+        skip_serve_countdown(&mut app);
+        for _ in 0..(MATCH_POINT_ZOOM_SECS / TEST_FRAME.as_secs_f32()).ceil() as u32 + 5 {
+            app.update();
+        }
+        let mut camera = app.world.query_filtered::<&OrthographicProjection, With<Camera2d>>();
+        let scale = camera.single(&app.world).scale;
+        assert!(scale < 1.);
+        assert!(scale >= 1. - MATCH_POINT_ZOOM);
+
+        // Close enough that the paddles are still on screen
+        let mut paddles = app.world.query_filtered::<(&Position, &Shape), With<Paddle>>();
+        for (position, shape) in paddles.iter(&app.world) {
+            let far_edge = position.0.abs() + shape.0 / 2.;
+            assert!(far_edge.x <= 400. * scale && far_edge.y <= 300. * scale);
+        }
+
+        enter_state(&mut app, GameState::MainMenu);
+        assert_eq!(camera.single(&app.world).scale, 1.);
+    }
+
+    fn opening_serve_with_seed(seed: u64) -> Vec2 {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.insert_resource(RngResource::seeded(seed));
+        start_match(&mut app);
+
+        let mut balls = app.world.query_filtered::<&AwaitingServe, With<Ball>>();
+        balls.single(&app.world).0
+    }
+
+    #[test]
+    fn opening_serve_goes_either_way_but_follows_the_seed() {
+        assert_eq!(opening_serve_with_seed(3), opening_serve_with_seed(3));
+
+        let directions: Vec<f32> =
+            (0..16).map(|seed| opening_serve_with_seed(seed).x.signum()).collect();
+        assert!(directions.contains(&1.) && directions.contains(&-1.));
+    }
+
+    #[test]
+    fn turning_vsync_off_changes_the_window_present_mode() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.update();
+        app.world.resource_mut::<WindowSettings>().vsync = false;
+        app.update();
+
+        let mut windows = app.world.query::<&Window>();
+        assert_eq!(windows.single(&app.world).present_mode, PresentMode::AutoNoVsync);
+    }
+
+    #[test]
+    fn a_state_change_fades_out_switches_over_then_fades_back_in() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.add_plugins(menu::TransitionPlugin);
+        app.update();
+        let fade_secs = app.world.resource::<GameConfig>().fade_secs;
+        let fade_frames = (fade_secs / TEST_FRAME.as_secs_f32()).ceil() as usize;
+
+        // Held keys stay held all the way through, only menu presses are ignored
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::Up);
+        app.world.resource_mut::<menu::Transition>().target = Some(GameState::Playing);
+
+        let state = |app: &App| *app.world.resource::<State<GameState>>().get();
+        let darkness = |app: &App| app.world.resource::<menu::Transition>().darkness;
+
+        // Darkening, still in the menu until the screen is black
+        let mut frames = 0;
+        while app.world.resource::<menu::Transition>().target.is_some() {
+            assert_eq!(state(&app), GameState::MainMenu);
+            let before = darkness(&app);
+            app.update();
+            assert!(darkness(&app) > before);
+            assert!(app.world.resource::<Input<KeyCode>>().pressed(KeyCode::Up));
+            frames += 1;
+            assert!(frames <= fade_frames + 1);
+        }
+        assert_eq!(darkness(&app), 1.);
+
+        // Then the switch, and clearing again in the new state
+        app.update();
+        assert_eq!(state(&app), GameState::Playing);
+        for _ in 0..=fade_frames {
+            app.update();
+        }
+        assert_eq!(state(&app), GameState::Playing);
+        assert_eq!(darkness(&app), 0.);
+        assert!(app.world.resource::<Input<KeyCode>>().pressed(KeyCode::Up));
+    }
+
+    #[test]
+    fn holding_a_rebound_slide_key_moves_the_player_gutter_paddle() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::FourPaddles;
+        app.world.resource_mut::<InputBindings>().rebind(InputAction::Left, KeyCode::A);
+        start_match(&mut app);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::A);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        // The AI's gutter paddle may be lining up for the serve, so only the
+        // player's is checked
+        let player_side = app.world.resource::<PlayerSide>().0;
+        let mut gutter_paddles =
+            app.world.query_filtered::<(&Position, &Side), With<GutterPaddle>>();
+        let (position, _) =
+            gutter_paddles.iter(&app.world).find(|(_, side)| **side == player_side).unwrap();
+        assert!(position.0.x < 0.);
+    }
+
+    #[test]
+    fn the_ai_gutter_paddle_chases_a_ball_heading_for_its_gutter() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        *app.world.resource_mut::<GameMode>() = GameMode::FourPaddles;
+        start_match(&mut app);
+        skip_serve_countdown(&mut app);
+
+        // Only the AI's gutter paddle aims, with the same error and reaction
+        // time as the AI's other paddle
+        let mut ai_gutter_paddles =
+            app.world.query_filtered::<Entity, (With<GutterPaddle>, With<AiAim>)>();
+        let ai_gutter_paddle = ai_gutter_paddles.single(&app.world);
+        let gutter_y = app.world.get::<Position>(ai_gutter_paddle).unwrap().0.y;
+
+        // Drifting slowly toward that gutter, well off to one side
+        let ball = app.world.query_filtered::<Entity, With<Ball>>().single(&app.world);
+        let start = Vec2::new(200., gutter_y / 2.);
+        app.world.get_mut::<Position>(ball).unwrap().0 = start;
+        app.world.get_mut::<PreviousPosition>(ball).unwrap().0 = start;
+        app.world.get_mut::<Velocity>(ball).unwrap().0 = Vec2::new(0., gutter_y.signum());
+
+        for _ in 0..20 {
+            app.update();
+        }
+        assert!(app.world.get::<Position>(ai_gutter_paddle).unwrap().0.x > 0.);
+    }
+
+    #[test]
+    fn a_test_frame_is_exactly_one_fixed_step() {
+        let mut app = build_test_app(Vec2::new(800., 600.));
+        app.update();
+        assert_eq!(app.world.resource::<Time<Fixed>>().timestep(), TEST_FRAME);
+    }
+}
@@ -1,12 +1,140 @@
-use bevy::prelude::*;
+use bevy::{
+    app::AppExit,
+    ecs::system::SystemParam,
+    prelude::*,
+    sprite::{collide_aabb::Collision, MaterialMesh2dBundle},
+};
+use rand::Rng;
 
-use crate::GameState;
+use crate::{
+    despawn_with, reflect_velocity, save::HighScore, serve_ball, step_ball, swept_collision,
+    toggle_pause, AssistMode, CosmeticRng, CourtDimensions, Difficulty, GameConfig, GameMode,
+    GameState, Gutter, InputAction, InputBindings, InputMode, MatchStats, Paused, PlayerSide,
+    Position, PreviousPosition, RallySpeed, Score, Shape, Side, Theme, Velocity, Volume,
+    WindowSettings, Winner, ZLayer, BALL_Z, MAX_BOUNCE_ANGLE,
+};
 pub struct MenuPlugins;
 
 impl PluginGroup for MenuPlugins {
     fn build(self) -> bevy::app::PluginGroupBuilder {
         bevy::app::PluginGroupBuilder::start::<MenuPlugins>()
+        .add(MenuNavigationPlugin)
         .add(MainMenuPlugin)
+        .add(SettingsMenuPlugin)
+        .add(PauseMenuPlugin)
+        .add(GameOverPlugin)
+        .add(TransitionPlugin)
+    }
+}
+
+// Up and down through the items of whichever menu is open, and Enter to pick
+// one. Each menu spawns a `MenuNavigation` with `MenuItem` children and
+// handles the `MenuActivated` events for its own items.
+struct MenuNavigationPlugin;
+
+impl Plugin for MenuNavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MenuActivated>().add_systems(
+            Update,
+            (
+                track_menu_items,
+                navigate_menus.after(track_menu_items).run_if(not(transitioning)),
+                highlight_menu_items.after(navigate_menus),
+                activate_menu_items.after(navigate_menus).run_if(not(transitioning)),
+            ),
+        );
+    }
+}
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.35, 0.35, 0.35);
+
+// A menu that can be stepped through from the keyboard. `items` are its
+// `MenuItem` children, top to bottom.
+#[derive(Component, Default)]
+struct MenuNavigation {
+    items: Vec<Entity>,
+    selected: usize,
+}
+
+impl MenuNavigation {
+    fn selected_item(&self) -> Option<Entity> {
+        self.items.get(self.selected).copied()
+    }
+}
+
+// Something in a menu that can be highlighted and picked
+#[derive(Component)]
+struct MenuItem;
+
+// Sent when Enter is pressed on a menu item, naming the item
+#[derive(Event)]
+struct MenuActivated(Entity);
+
+// Menus spawn their items as children, which only show up once the spawn
+// commands have run
+fn track_menu_items(
+    mut menus: Query<(&mut MenuNavigation, &Children), Changed<Children>>,
+    items: Query<(), With<MenuItem>>,
+){
+    for (mut menu, children) in &mut menus {
+        menu.items = children.iter().copied().filter(|child| items.contains(*child)).collect();
+        menu.selected = menu.selected.min(menu.items.len().saturating_sub(1));
+    }
+}
+
+fn navigate_menus(keyboard_input: Res<Input<KeyCode>>, mut menus: Query<&mut MenuNavigation>) {
+    for mut menu in &mut menus {
+        let count = menu.items.len();
+        if count == 0 {
+            continue;
+        }
+
+        // Wrap around at either end
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            menu.selected = (menu.selected + count - 1) % count;
+        } else if keyboard_input.just_pressed(KeyCode::Down) {
+            menu.selected = (menu.selected + 1) % count;
+        }
+    }
+}
+
+fn highlight_menu_items(
+    menus: Query<&MenuNavigation>,
+    mut items: Query<&mut BackgroundColor, With<MenuItem>>,
+){
+    for menu in &menus {
+        for (index, item) in menu.items.iter().enumerate() {
+            if let Ok(mut background) = items.get_mut(*item) {
+                *background = if index == menu.selected {
+                    SELECTED_BUTTON.into()
+                } else {
+                    NORMAL_BUTTON.into()
+                };
+            }
+        }
+    }
+}
+
+fn activate_menu_items(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    menus: Query<&MenuNavigation>,
+    mut events: EventWriter<MenuActivated>,
+){
+    if !keyboard_input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let mut activated = false;
+    for item in menus.iter().filter_map(MenuNavigation::selected_item) {
+        events.send(MenuActivated(item));
+        activated = true;
+    }
+
+    // One press picks one item. Whatever it opens shouldn't see the same
+    // press and pick something straight away too.
+    if activated {
+        keyboard_input.reset(KeyCode::Return);
     }
 }
 
@@ -14,23 +142,1099 @@ struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::MainMenu),spawn_main_menu)
-        .add_systems(OnExit(GameState::MainMenu), close_menu);
+        app.add_systems(OnEnter(GameState::MainMenu), (spawn_main_menu, spawn_demo_ball))
+        .add_systems(OnExit(GameState::MainMenu), (close_menu, despawn_with::<DemoBall>))
+        .add_systems(
+            Update,
+            activate_button.after(activate_menu_items).run_if(in_state(GameState::MainMenu)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (move_demo_ball, bounce_demo_ball.after(move_demo_ball))
+                .run_if(in_state(GameState::MainMenu)),
+        )
+        .add_systems(Update, name_state::<GameState>.run_if(state_changed::<GameState>()));
     }
 }
 
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum MenuButton {
+    Play,
+    TwoPlayers,
+    Practice,
+    Survival,
+    Timed,
+    FourPaddles,
+    Settings,
+    Quit,
+}
+
+// The buttons on the main menu, top to bottom
+const MENU_BUTTONS: [(MenuButton, &str); 8] = [
+    (MenuButton::Play, "PLAY"),
+    (MenuButton::TwoPlayers, "2 PLAYERS"),
+    (MenuButton::Practice, "PRACTICE"),
+    (MenuButton::Survival, "SURVIVAL"),
+    (MenuButton::Timed, "TIMED"),
+    (MenuButton::FourPaddles, "4 PADDLES"),
+    (MenuButton::Settings, "SETTINGS"),
+    (MenuButton::Quit, "QUIT"),
+];
+
 fn spawn_main_menu(
-    commands: Commands
+    mut commands: Commands,
+    high_score: Res<HighScore>,
 ){
     println!("Spawn main menu");
+
+    commands.spawn((
+        MainMenu,
+        MenuNavigation::default(),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+    ))
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("HIGH SCORE: {}", high_score.0),
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+
+        for (button, label) in MENU_BUTTONS {
+            parent.spawn((
+                button,
+                MenuItem,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(250.0),
+                        height: Val::Px(55.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+            });
+        }
+    });
+}
+
+fn activate_button(
+    mut events: EventReader<MenuActivated>,
+    buttons: Query<&MenuButton>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+){
+    let Some(button) = events.read().find_map(|event| buttons.get(event.0).ok()) else {
+        return;
+    };
+
+    match button {
+        MenuButton::Play => {
+            *mode = GameMode::VsAi;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::TwoPlayers => {
+            *mode = GameMode::TwoPlayer;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::Practice => {
+            *mode = GameMode::Practice;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::Survival => {
+            *mode = GameMode::Survival;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::Timed => {
+            *mode = GameMode::Timed;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::FourPaddles => {
+            *mode = GameMode::FourPaddles;
+            next_state.set(GameState::Playing);
+        }
+        MenuButton::Settings => next_state.set(GameState::SettingsMenu),
+        MenuButton::Quit => app_exit_events.send(AppExit),
+    }
 }
 
 fn close_menu(
+    mut commands: Commands,
+    menu: Query<Entity, With<MainMenu>>,
 ){
+    for entity in &menu {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
+// A ball that bounces around the court behind the main menu. It isn't a `Ball`,
+// so nothing in the game proper (scoring, paddles, sounds) ever sees it.
+#[derive(Component)]
+struct DemoBall;
+
+// How see-through the demo ball is, so it stays in the background
+const DEMO_BALL_ALPHA: f32 = 0.5;
+
+fn spawn_demo_ball(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>,
+    theme: Res<Theme>,
+    mut rng: ResMut<CosmeticRng>,
+){
+    // Only there to look at, so it leaves the gameplay generator alone
+    let direction = if rng.0.gen() { 1. } else { -1. };
+
+    commands.spawn((
+        DemoBall,
+        Shape(Vec2::splat(config.ball_size)),
+        serve_ball(direction, MAX_BOUNCE_ANGLE, &mut rng.0),
+        Position(Vec2::ZERO),
+        PreviousPosition(Vec2::ZERO),
+        RallySpeed(config.ball_speed),
+        ZLayer(BALL_Z),
+        MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Circle::new(config.ball_size / 2.))).into(),
+            material: materials.add(ColorMaterial::from(theme.ball().with_a(DEMO_BALL_ALPHA))),
+            ..default()
+        },
+    ));
+}
+
+fn move_demo_ball(
+    mut balls: Query<
+        (&mut Position, &mut PreviousPosition, &Velocity, &RallySpeed),
+        With<DemoBall>,
+    >,
+    time: Res<Time>,
+){
+    for (mut position, mut previous_position, velocity, speed) in &mut balls {
+        step_ball(&mut position, &mut previous_position, velocity, speed.0 * time.delta_seconds());
+    }
+}
+
+// Bounces the demo ball off the gutters like the real ball, and off the sides
+// of the window where there's no paddle to stop it
+fn bounce_demo_ball(
+    mut balls: Query<
+        (&mut Velocity, &mut Position, &PreviousPosition, &Shape),
+        (With<DemoBall>, Without<Gutter>),
+    >,
+    gutters: Query<(&Position, &Shape), With<Gutter>>,
+    court: Res<CourtDimensions>,
+){
+    for (mut velocity, mut position, previous_position, shape) in &mut balls {
+        let first_hit = gutters
+            .iter()
+            .filter_map(|(gutter_position, gutter_shape)| {
+                swept_collision(
+                    previous_position.0,
+                    position.0,
+                    shape.0,
+                    gutter_position.0,
+                    gutter_shape.0,
+                )
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        if let Some((time_of_impact, collision)) = first_hit {
+            position.0 = previous_position.0.lerp(position.0, time_of_impact);
+            velocity.0 = reflect_velocity(velocity.0, collision);
+        }
+
+        let max_x = (court.width - shape.0.x) / 2.;
+        if position.0.x > max_x {
+            position.0.x = max_x;
+            velocity.0 = reflect_velocity(velocity.0, Collision::Left);
+        } else if position.0.x < -max_x {
+            position.0.x = -max_x;
+            velocity.0 = reflect_velocity(velocity.0, Collision::Right);
+        }
+    }
+}
+
+struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+        .init_resource::<SettingsOverlay>()
+        .add_systems(OnEnter(GameState::SettingsMenu), spawn_settings_menu)
+        .add_systems(OnExit(GameState::SettingsMenu), close_settings_menu)
+        .add_systems(
+            Update,
+            (
+                // Before anything else looks at the keyboard, so the key that
+                // gets bound isn't also acted on
+                rebind_key
+                    .before(navigate_menus)
+                    .before(activate_menu_items)
+                    .run_if(not(transitioning)),
+                (
+                    adjust_setting.after(navigate_menus),
+                    activate_setting.after(activate_menu_items),
+                    leave_settings,
+                )
+                    .run_if(not_rebinding.and_then(not(transitioning))),
+                update_settings_text.after(activate_setting),
+            )
+                // Before pausing, so keys used up by the overlay don't also
+                // unpause the match underneath it
+                .before(toggle_pause)
+                .run_if(in_state(GameState::SettingsMenu).or_else(settings_overlay_open)),
+        )
+        // The same menu, opened on top of a paused match
+        .add_systems(
+            Update,
+            (
+                close_settings_menu,
+                spawn_settings_menu.after(close_settings_menu).run_if(settings_overlay_open),
+            )
+                .run_if(resource_changed::<SettingsOverlay>())
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            close_settings_on_resume.after(toggle_pause).run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            OnExit(GameState::Playing),
+            (close_settings_menu, close_settings_overlay),
+        );
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SettingsItem {
+    BallSpeed,
+    PaddleSpeed,
+    AiSpeed,
+    Difficulty,
+    Handicap,
+    Gutters,
+    Theme,
+    Volume,
+    Vsync,
+    Controls,
+    Assist,
+    PlayerSide,
+    CurveBall,
+    WinTarget,
+    WinByTwo,
+    UpKey,
+    DownKey,
+    LeftKey,
+    RightKey,
+    Back,
+}
+
+// The rows of the settings menu, top to bottom
+const SETTINGS_ITEMS: [SettingsItem; 20] = [
+    SettingsItem::BallSpeed,
+    SettingsItem::PaddleSpeed,
+    SettingsItem::AiSpeed,
+    SettingsItem::Difficulty,
+    SettingsItem::Handicap,
+    SettingsItem::Gutters,
+    SettingsItem::Theme,
+    SettingsItem::Volume,
+    SettingsItem::Vsync,
+    SettingsItem::Controls,
+    SettingsItem::Assist,
+    SettingsItem::PlayerSide,
+    SettingsItem::CurveBall,
+    SettingsItem::WinTarget,
+    SettingsItem::WinByTwo,
+    SettingsItem::UpKey,
+    SettingsItem::DownKey,
+    SettingsItem::LeftKey,
+    SettingsItem::RightKey,
+    SettingsItem::Back,
+];
+
+const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+const THEMES: [Theme; 3] = [Theme::Classic, Theme::Neon, Theme::Monochrome];
+
+const HANDICAPS: [Option<Side>; 3] = [None, Some(Side::Left), Some(Side::Right)];
+
+const INPUT_MODES: [InputMode; 3] = [InputMode::Keyboard, InputMode::Gamepad, InputMode::Mouse];
+
+// Everything the settings menu shows a value for, apart from the key bindings
+#[derive(SystemParam)]
+struct Settings<'w> {
+    config: Res<'w, GameConfig>,
+    difficulty: Res<'w, Difficulty>,
+    theme: Res<'w, Theme>,
+    volume: Res<'w, Volume>,
+    input_mode: Res<'w, InputMode>,
+    assist: Res<'w, AssistMode>,
+    player_side: Res<'w, PlayerSide>,
+    window_settings: Res<'w, WindowSettings>,
+}
+
+// The same settings, for changing them
+#[derive(SystemParam)]
+struct SettingsMut<'w> {
+    config: ResMut<'w, GameConfig>,
+    difficulty: ResMut<'w, Difficulty>,
+    theme: ResMut<'w, Theme>,
+    volume: ResMut<'w, Volume>,
+    input_mode: ResMut<'w, InputMode>,
+    assist: ResMut<'w, AssistMode>,
+    player_side: ResMut<'w, PlayerSide>,
+    window_settings: ResMut<'w, WindowSettings>,
+}
+
+// The action waiting for the next key press to be bound to it, if any
+#[derive(Resource, Default)]
+struct Rebinding(Option<InputAction>);
+
+fn not_rebinding(rebinding: Res<Rebinding>) -> bool {
+    rebinding.0.is_none()
+}
+
+// Whether the settings menu is open over a paused match, rather than on its
+// own screen from the main menu
+#[derive(Resource, Default)]
+struct SettingsOverlay(bool);
+
+fn settings_overlay_open(overlay: Res<SettingsOverlay>) -> bool {
+    overlay.0
+}
+
+fn close_settings_overlay(mut overlay: ResMut<SettingsOverlay>) {
+    overlay.0 = false;
+}
+
+// The pause key still works with the settings open, and there's no pause
+// menu to go back to once the match is running again
+fn close_settings_on_resume(paused: Res<Paused>, mut overlay: ResMut<SettingsOverlay>) {
+    if !paused.0 && overlay.0 {
+        overlay.0 = false;
+    }
+}
+
+fn spawn_settings_menu(mut commands: Commands, mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+
+    commands.spawn((
+        SettingsMenu,
+        MenuNavigation::default(),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            ..default()
+        },
+    ))
+    // Smaller than the main menu buttons so every row fits in the window
+    .with_children(|parent| {
+        for item in SETTINGS_ITEMS {
+            // The labels are filled in by `update_settings_text`
+            parent.spawn((
+                item,
+                MenuItem,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    padding: UiRect::axes(Val::Px(20.0), Val::Px(2.0)),
+                    ..default()
+                })
+                .with_background_color(NORMAL_BUTTON),
+            ));
+        }
+    });
+}
+
+fn adjust_setting(
+    keyboard_input: Res<Input<KeyCode>>,
+    menus: Query<&MenuNavigation, With<SettingsMenu>>,
+    items: Query<&SettingsItem>,
+    settings: SettingsMut,
+){
+    let step = if keyboard_input.just_pressed(KeyCode::Right) {
+        1.
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        -1.
+    } else {
+        return;
+    };
+    let Some(item) = menus
+        .iter()
+        .find_map(MenuNavigation::selected_item)
+        .and_then(|item| items.get(item).ok())
+    else {
+        return;
+    };
+
+    let SettingsMut {
+        mut config,
+        mut difficulty,
+        mut theme,
+        mut volume,
+        mut input_mode,
+        mut assist,
+        mut player_side,
+        mut window_settings,
+    } = settings;
+
+    // The ball speed stays under the rally speed cap so rallies can still
+    // speed up from wherever it starts
+    match item {
+        SettingsItem::BallSpeed => {
+            config.ball_speed = (config.ball_speed + step * 60.).clamp(120., 480.);
+        }
+        SettingsItem::PaddleSpeed => {
+            config.paddle_speed = (config.paddle_speed + step * 60.).clamp(120., 600.);
+        }
+        SettingsItem::AiSpeed => {
+            config.ai_speed = (config.ai_speed + step * 0.1).clamp(0.2, 1.);
+        }
+        SettingsItem::Difficulty => *difficulty = step_choice(&DIFFICULTIES, *difficulty, step),
+        SettingsItem::Handicap => config.handicap = step_choice(&HANDICAPS, config.handicap, step),
+        SettingsItem::Gutters => {
+            config.gutter_height = (config.gutter_height + step * 10.).clamp(10., 80.);
+        }
+        SettingsItem::Theme => *theme = step_choice(&THEMES, *theme, step),
+        SettingsItem::Volume => *volume = volume.step(step),
+        SettingsItem::Vsync => window_settings.vsync = step > 0.,
+        SettingsItem::Controls => *input_mode = step_choice(&INPUT_MODES, *input_mode, step),
+        SettingsItem::Assist => assist.0 = step > 0.,
+        SettingsItem::PlayerSide => {
+            player_side.0 = if step > 0. { Side::Right } else { Side::Left };
+        }
+        SettingsItem::CurveBall => config.curve_ball = step > 0.,
+        SettingsItem::WinTarget => {
+            config.win_target = (config.win_target as f32 + step).clamp(1., 21.) as u32;
+        }
+        SettingsItem::WinByTwo => config.win_by_two = step > 0.,
+        SettingsItem::UpKey
+        | SettingsItem::DownKey
+        | SettingsItem::LeftKey
+        | SettingsItem::RightKey
+        | SettingsItem::Back => {}
+    }
+}
+
+// The option next to `current` in the direction of `step`, stopping at either
+// end of the list rather than wrapping around
+fn step_choice<T: PartialEq + Copy>(options: &[T], current: T, step: f32) -> T {
+    let index = options.iter().position(|option| *option == current).unwrap_or(0);
+    let next = if step > 0. {
+        (index + 1).min(options.len() - 1)
+    } else {
+        index.saturating_sub(1)
+    };
+    options[next]
+}
+
+// Enter on one of the key rows waits for a key, and Enter on the last row
+// goes back
+fn activate_setting(
+    mut events: EventReader<MenuActivated>,
+    items: Query<&SettingsItem>,
+    mut rebinding: ResMut<Rebinding>,
+    mut overlay: ResMut<SettingsOverlay>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    let Some(item) = events.read().find_map(|event| items.get(event.0).ok()) else {
+        return;
+    };
+
+    match item {
+        SettingsItem::UpKey => rebinding.0 = Some(InputAction::Up),
+        SettingsItem::DownKey => rebinding.0 = Some(InputAction::Down),
+        SettingsItem::LeftKey => rebinding.0 = Some(InputAction::Left),
+        SettingsItem::RightKey => rebinding.0 = Some(InputAction::Right),
+        SettingsItem::Back => leave(&mut overlay, &mut next_state),
+        _ => {}
+    }
+}
+
+// The next key pressed once a key row has been picked is bound to that
+// action. Escape gives up without changing anything.
+fn rebind_key(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+){
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+
+    let key = if keyboard_input.just_pressed(KeyCode::Escape) {
+        KeyCode::Escape
+    } else if let Some(key) = keyboard_input.get_just_pressed().next().copied() {
+        bindings.rebind(action, key);
+        key
+    } else {
+        return;
+    };
+    rebinding.0 = None;
+    // Otherwise binding the pause key would pause or unpause the match
+    // behind the overlay too, and binding Up or Down would move the selection
+    keyboard_input.reset(key);
+}
+
+fn update_settings_text(
+    settings: Settings,
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+    mut items: Query<(&SettingsItem, &mut Text)>,
+){
+    let Settings {
+        config,
+        difficulty,
+        theme,
+        volume,
+        input_mode,
+        assist,
+        player_side,
+        window_settings,
+    } = settings;
+    let key_label = |label: &str, action| {
+        if rebinding.0 == Some(action) {
+            format!("{label}  < PRESS A KEY >")
+        } else {
+            format!("{label}  < {:?} >", bindings.key(action)).to_uppercase()
+        }
+    };
+
+    for (item, mut text) in &mut items {
+        text.sections[0].value = match item {
+            SettingsItem::BallSpeed => format!("BALL SPEED  < {} >", config.ball_speed),
+            SettingsItem::PaddleSpeed => format!("PADDLE SPEED  < {} >", config.paddle_speed),
+            SettingsItem::AiSpeed => {
+                format!("AI SPEED  < {:.0}% >", config.ai_speed * 100.)
+            }
+            SettingsItem::Difficulty => {
+                format!("DIFFICULTY  < {:?} >", *difficulty).to_uppercase()
+            }
+            SettingsItem::Handicap => match config.handicap {
+                None => "HANDICAP  < NONE >".to_string(),
+                Some(side) => format!("HANDICAP  < {:?} >", side).to_uppercase(),
+            },
+            SettingsItem::Gutters => format!("GUTTERS  < {} >", config.gutter_height),
+            SettingsItem::Theme => format!("THEME  < {:?} >", *theme).to_uppercase(),
+            SettingsItem::Volume => format!("VOLUME  < {:.0}% >", volume.0 * 100.),
+            SettingsItem::Vsync => format!("VSYNC  < {} >", on_off(window_settings.vsync)),
+            SettingsItem::Controls => format!("CONTROLS  < {:?} >", *input_mode).to_uppercase(),
+            SettingsItem::Assist => format!("ASSIST  < {} >", on_off(assist.0)),
+            SettingsItem::PlayerSide => {
+                format!("YOUR SIDE  < {:?} >", player_side.0).to_uppercase()
+            }
+            SettingsItem::CurveBall => format!("CURVE BALL  < {} >", on_off(config.curve_ball)),
+            SettingsItem::WinTarget => format!("FIRST TO  < {} >", config.win_target),
+            SettingsItem::WinByTwo => format!("WIN BY TWO  < {} >", on_off(config.win_by_two)),
+            SettingsItem::UpKey => key_label("MOVE UP", InputAction::Up),
+            SettingsItem::DownKey => key_label("MOVE DOWN", InputAction::Down),
+            // Only used for the gutter paddle in four paddle mode
+            SettingsItem::LeftKey => key_label("SLIDE LEFT", InputAction::Left),
+            SettingsItem::RightKey => key_label("SLIDE RIGHT", InputAction::Right),
+            SettingsItem::Back => "BACK".to_string(),
+        };
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+fn leave_settings(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut overlay: ResMut<SettingsOverlay>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        leave(&mut overlay, &mut next_state);
+        // Escape is also the pause key, and this press is only meant to
+        // close the overlay
+        keyboard_input.reset(KeyCode::Escape);
+    }
+}
+
+// Goes back to wherever the settings were opened from: the main menu, or the
+// pause menu of the match underneath
+fn leave(overlay: &mut SettingsOverlay, next_state: &mut NextState<GameState>) {
+    if overlay.0 {
+        overlay.0 = false;
+    } else {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn close_settings_menu(
+    mut commands: Commands,
+    menu: Query<Entity, With<SettingsMenu>>,
+){
+    for entity in &menu {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (
+                    close_pause_menu,
+                    spawn_pause_menu.after(close_pause_menu).run_if(pause_menu_open),
+                )
+                    .run_if(
+                        resource_changed::<Paused>()
+                            .or_else(resource_changed::<SettingsOverlay>()),
+                    ),
+                activate_pause_button.after(activate_menu_items),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        // Leaving play for the main menu takes the rest of the match with it
+        .add_systems(OnExit(GameState::Playing), close_pause_menu);
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PauseButton {
+    Resume,
+    Settings,
+    MainMenu,
+}
+
+// The buttons on the pause menu, top to bottom
+const PAUSE_BUTTONS: [(PauseButton, &str); 3] = [
+    (PauseButton::Resume, "RESUME"),
+    (PauseButton::Settings, "SETTINGS"),
+    (PauseButton::MainMenu, "MAIN MENU"),
+];
+
+// The pause menu shows whenever the match is paused, except while the
+// settings are open on top of it
+fn pause_menu_open(paused: Res<Paused>, overlay: Res<SettingsOverlay>) -> bool {
+    paused.0 && !overlay.0
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands.spawn((
+        PauseMenu,
+        MenuNavigation::default(),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+    ))
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "PAUSED",
+            TextStyle {
+                font_size: 60.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+
+        for (button, label) in PAUSE_BUTTONS {
+            parent.spawn((
+                button,
+                MenuItem,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(250.0),
+                        height: Val::Px(65.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+            });
+        }
+    });
+}
+
+fn activate_pause_button(
+    mut events: EventReader<MenuActivated>,
+    buttons: Query<&PauseButton>,
+    mut paused: ResMut<Paused>,
+    mut overlay: ResMut<SettingsOverlay>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    let Some(button) = events.read().find_map(|event| buttons.get(event.0).ok()) else {
+        return;
+    };
+
+    match button {
+        PauseButton::Resume => paused.0 = false,
+        PauseButton::Settings => overlay.0 = true,
+        PauseButton::MainMenu => next_state.set(GameState::MainMenu),
+    }
+}
+
+fn close_pause_menu(
+    mut commands: Commands,
+    menu: Query<Entity, With<PauseMenu>>,
+){
+    for entity in &menu {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DemoRestart>()
+        .add_systems(OnEnter(GameState::GameOver), (spawn_game_over, reset_demo_restart))
+        .add_systems(OnExit(GameState::GameOver), close_game_over)
+        .add_systems(
+            Update,
+            (
+                (restart_match, return_to_main_menu).run_if(not(transitioning)),
+                restart_demo_match,
+            )
+                .run_if(in_state(GameState::GameOver)),
+        );
+    }
+}
+
+#[derive(Component)]
+struct GameOver;
+
+fn spawn_game_over(
+    mut commands: Commands,
+    winner: Res<Winner>,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    mode: Res<GameMode>,
+    player_side: Res<PlayerSide>,
+){
+    // In practice the only way for a match to end is running out of misses
+    let headline = match (*winner, *mode) {
+        (_, GameMode::Practice) => "Practice over!",
+        (_, GameMode::Survival) => "Out of lives!",
+        (Winner::Left, GameMode::DemoMatch) => "The left AI wins!",
+        (Winner::Right, GameMode::DemoMatch) => "The right AI wins!",
+        (Winner::Draw, _) => "Draw!",
+        // Two people playing, so "you" could be either of them
+        (winner, GameMode::TwoPlayer) if winner == Winner::from(player_side.0) => "Player 1 wins!",
+        (_, GameMode::TwoPlayer) => "Player 2 wins!",
+        (winner, _) if winner == Winner::from(player_side.0) => "You win!",
+        _ => "The AI wins!",
+    };
+
+    // A summary of the match under the headline
+    let secs = stats.match_secs as u32;
+    let summary = [
+        format!("FINAL SCORE: {} - {}", score.left, score.right),
+        format!("LONGEST RALLY: {}", stats.longest_rally),
+        format!("PADDLE HITS: {}", stats.paddle_hits),
+        format!("MATCH TIME: {}:{:02}", secs / 60, secs % 60),
+        format!("TOP SPEED: {:.0}", stats.top_speed),
+    ];
+
+    commands.spawn((
+        GameOver,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+    ))
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            headline,
+            TextStyle {
+                font_size: 60.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+
+        for line in summary {
+            parent.spawn(TextBundle::from_section(
+                line,
+                TextStyle {
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        }
+
+        parent.spawn(TextBundle::from_section(
+            "Press Enter or R to play again, or Escape for the main menu",
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+    });
+}
+
+// Straight into another match in the same mode. Leaving `GameState::Playing`
+// already cleared away the last match's balls and paddles, and entering it
+// spawns fresh ones.
+fn restart_match(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut score: ResMut<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::R]) {
+        *score = Score::default();
+        commands.remove_resource::<Winner>();
+        next_state.set(GameState::Playing);
+    }
+}
+
+// How long a finished demo match shows who won before starting another
+const DEMO_RESTART_SECS: f32 = 3.;
+
+#[derive(Resource)]
+struct DemoRestart(Timer);
+
+impl Default for DemoRestart {
+    fn default() -> Self {
+        Self(Timer::from_seconds(DEMO_RESTART_SECS, TimerMode::Once))
+    }
+}
+
+fn reset_demo_restart(mut restart: ResMut<DemoRestart>) {
+    restart.0.reset();
+}
+
+// Demo matches carry on by themselves, so nobody has to be there to press a
+// key for the next one
+fn restart_demo_match(
+    mut commands: Commands,
+    time: Res<Time>,
+    mode: Res<GameMode>,
+    mut restart: ResMut<DemoRestart>,
+    mut score: ResMut<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if *mode != GameMode::DemoMatch || !restart.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    *score = Score::default();
+    commands.remove_resource::<Winner>();
+    next_state.set(GameState::Playing);
+}
+
+fn return_to_main_menu(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn close_game_over(
+    mut commands: Commands,
+    game_over: Query<Entity, With<GameOver>>,
+){
+    for entity in &game_over {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Fades to black and back between states. Every state change goes through
+// here: `start_transition` takes over whatever `NextState` the rest of the game
+// asked for and only passes it on once the screen is dark.
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transition>()
+        .add_systems(Startup, spawn_fade_overlay)
+        .add_systems(
+            PostUpdate,
+            (
+                start_transition,
+                run_transition.after(start_transition),
+                show_fade.after(run_transition),
+            ),
+        );
+    }
+}
+
+// Covers the whole window, over everything else
+#[derive(Component)]
+struct FadeOverlay;
+
+// The state being faded over to, and how dark the screen is on the way, from
+// clear at 0 to black at 1
+#[derive(Resource, Default)]
+pub struct Transition {
+    pub target: Option<GameState>,
+    pub darkness: f32,
+}
+
+// Menu keys do nothing mid-fade, so a second press can't start another state
+// change on top of the first. Keys held down still count as held, so a paddle
+// doesn't stop dead when a match starts with its key already down.
+fn transitioning(transition: Res<Transition>) -> bool {
+    transition.target.is_some() || transition.darkness > 0.
+}
+
+fn spawn_fade_overlay(mut commands: Commands) {
+    commands.spawn((
+        FadeOverlay,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+    ));
+}
+
+fn start_transition(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut transition: ResMut<Transition>,
+    config: Res<GameConfig>,
+){
+    if config.fade_secs <= 0. {
+        return;
+    }
+    let Some(state) = next_state.0.take() else {
+        return;
+    };
+
+    // Anything asked for while already fading out is dropped, so one change
+    // can't be triggered twice. A change asked for while the last one is
+    // still fading in darkens the screen again from wherever it's got to.
+    if transition.target.is_none() {
+        transition.target = Some(state);
+    }
+}
+
+// Darkens the screen until it's black, switches state, then lets it clear
+fn run_transition(
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut transition: ResMut<Transition>,
+    config: Res<GameConfig>,
+){
+    let step = if config.fade_secs > 0. {
+        time.delta_seconds() / config.fade_secs
+    } else {
+        1.
+    };
+
+    match transition.target {
+        Some(state) => {
+            transition.darkness = (transition.darkness + step).min(1.);
+            if transition.darkness >= 1. {
+                next_state.set(state);
+                transition.target = None;
+            }
+        }
+        None => transition.darkness = (transition.darkness - step).max(0.),
+    }
+}
+
+fn show_fade(
+    transition: Res<Transition>,
+    mut overlay: Query<&mut BackgroundColor, With<FadeOverlay>>,
+){
+    if !transition.is_changed() {
+        return;
+    }
+
+    for mut color in &mut overlay {
+        color.0 = Color::BLACK.with_a(transition.darkness);
+    }
 }
 
 fn name_state<T: States>(state: Res<State<T>>) {
     info!("Current state: {:?}", state.get());
 }
-
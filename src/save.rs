@@ -0,0 +1,127 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameMode, GameState, PlayerSide, Score, Volume};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // Disk reads wait for startup rather than happening while the app is
+        // still being put together
+        app.init_resource::<HighScore>()
+        .init_resource::<SaveData>()
+        .add_systems(PreStartup, load_save)
+        .add_systems(OnEnter(GameState::GameOver), record_high_score)
+        // The volume is saved once the settings are closed rather than on
+        // every step. The settings can also be opened over a paused match,
+        // which closes with the match at the latest.
+        .add_systems(OnExit(GameState::SettingsMenu), record_volume)
+        .add_systems(OnExit(GameState::Playing), record_volume);
+    }
+}
+
+// The most points a human player has scored in a single match
+#[derive(Resource, Default)]
+pub struct HighScore(pub u32);
+
+// Everything we keep between runs. Missing fields fall back to their defaults
+// so older save files still load. Kept as a resource too, matching what's
+// on disk.
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+struct SaveData {
+    high_score: u32,
+    volume: f32,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            high_score: 0,
+            volume: Volume::default().0,
+        }
+    }
+}
+
+fn save_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("bevy_pong").join("save.json"))
+}
+
+// A missing or unreadable save file isn't worth crashing over, we just start
+// from scratch
+fn load() -> SaveData {
+    let Some(path) = save_path() else {
+        return SaveData::default();
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+            warn!("Ignoring save file {}: {}", path.display(), error);
+            SaveData::default()
+        }),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => SaveData::default(),
+        Err(error) => {
+            warn!("Couldn't read save file {}: {}", path.display(), error);
+            SaveData::default()
+        }
+    }
+}
+
+fn load_save(mut commands: Commands) {
+    let data = load();
+    commands.insert_resource(HighScore(data.high_score));
+    commands.insert_resource(Volume(data.volume.clamp(0., 1.)));
+    commands.insert_resource(data);
+}
+
+fn store(data: &SaveData) {
+    let Some(path) = save_path() else {
+        warn!("No data directory to save to");
+        return;
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let contents = serde_json::to_string_pretty(data).map_err(io::Error::other)?;
+            fs::write(&path, contents)
+        });
+
+    if let Err(error) = result {
+        warn!("Couldn't write save file {}: {}", path.display(), error);
+    }
+}
+
+fn record_high_score(
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    player_side: Res<PlayerSide>,
+    mut high_score: ResMut<HighScore>,
+    mut saved: ResMut<SaveData>,
+) {
+    // Only the player's paddle is human when playing the AI
+    let best = match *mode {
+        GameMode::VsAi | GameMode::Timed | GameMode::FourPaddles => score.of(player_side.0),
+        GameMode::TwoPlayer => score.left.max(score.right),
+        // Nobody scores against a wall, and nobody's playing a demo match
+        GameMode::Practice | GameMode::Survival | GameMode::DemoMatch => return,
+    };
+
+    if best > high_score.0 {
+        high_score.0 = best;
+        saved.high_score = best;
+        store(&saved);
+    }
+}
+
+fn record_volume(volume: Res<Volume>, mut saved: ResMut<SaveData>) {
+    // Nothing to write if it ended up where it started
+    if saved.volume != volume.0 {
+        saved.volume = volume.0;
+        store(&saved);
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::AssetPlugin,
+    audio::AudioSource,
+    input::InputPlugin,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    window::WindowFocused,
+};
+
+use crate::{GamePlugin, GameState, SERVE_COUNTDOWN_SECS};
+
+// How much time passes each `app.update()` in a test app: exactly one fixed
+// update step, so tests can count steps without worrying about real time.
+// That's the default 60 Hz step rounded to the nearest nanosecond, the way
+// `Time<Fixed>` rounds it. Rounding down instead leaves each frame just short
+// of a step, and every so often a frame would run no fixed update at all.
+pub const TEST_FRAME: Duration = Duration::from_nanos(16_666_667);
+
+// Builds an app that runs the gameplay systems without a window, renderer or
+// audio device. Instead of a real window there's a plain `Window` entity of
+// the given size, which is all the gameplay systems read from it.
+//
+// The app starts in the main menu. Tests can move to `GameState::Playing`,
+// call `app.update()` and then query `Position`, `Velocity`, `Score` etc.
+pub fn build_test_app(window_size: Vec2) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(TEST_FRAME))
+        // Normally registered by the render, audio and window plugins
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<AudioSource>()
+        .add_event::<WindowFocused>()
+        .add_plugins(GamePlugin);
+
+    let mut window = Window::default();
+    window.resolution.set(window_size.x, window_size.y);
+    app.world.spawn(window);
+
+    app
+}
+
+// Moves the app into `state`, running the update that applies the transition
+pub fn enter_state<S: States>(app: &mut App, state: S) {
+    app.world.resource_mut::<NextState<S>>().set(state);
+    app.update();
+}
+
+// Starts a match from the main menu, with the ball waiting to be served
+pub fn start_match(app: &mut App) {
+    enter_state(app, GameState::Playing);
+}
+
+// Runs the app until the serve countdown is over and the ball is moving
+pub fn skip_serve_countdown(app: &mut App) {
+    // A few frames over, so the served ball has had time to get going
+    let frames = (SERVE_COUNTDOWN_SECS / TEST_FRAME.as_secs_f32()).ceil() as u32 + 5;
+    for _ in 0..frames {
+        app.update();
+    }
+}